@@ -1,39 +1,54 @@
-use std::fs::read_to_string;
+use std::fs::{self, read};
 use std::io::Write;
 use std::{
-    ffi::OsString,
     fs::{File, ReadDir},
-    path::PathBuf,
+    path::{Path, PathBuf},
 };
 use tempfile::tempfile;
 
-use crate::git_commands::GIT_ROOT;
+use crate::git_commands::git_work_dir;
 use crate::CONFIG_ROOT;
 
-pub fn backup_files(config_files: ReadDir) -> anyhow::Result<Vec<(OsString, File, String)>> {
+/// Backs up every file of the config directory, including the ones in sub-directories
+///
+/// Returns the path of each file relative to the config directory
+pub fn backup_files(config_files: ReadDir) -> anyhow::Result<Vec<(PathBuf, File, Vec<u8>)>> {
     let mut backups = Vec::new();
 
     for entry in config_files {
         let config_file = entry?;
 
         let path = config_file.path();
-        let contents = read_to_string(&path)?;
+        let filename = PathBuf::from(config_file.file_name());
+
+        if config_file.file_type()?.is_dir() {
+            for (nested_filename, file, contents) in backup_files(fs::read_dir(&path)?)? {
+                backups.push((filename.join(nested_filename), file, contents));
+            }
+            continue;
+        }
+
+        let contents = read(&path)?;
 
-        let filename = config_file.file_name();
         let mut destination_backed_up = tempfile()?;
 
-        write!(destination_backed_up, "{contents}")?;
+        destination_backed_up.write_all(&contents)?;
 
         backups.push((filename, destination_backed_up, contents));
     }
 
     Ok(backups)
 }
-pub fn restore_backup(file_name: &OsString, contents: &str) -> anyhow::Result<()> {
-    let path = GIT_ROOT.join(PathBuf::from(CONFIG_ROOT).join(file_name));
+pub fn restore_backup(file_name: &Path, contents: &[u8]) -> anyhow::Result<()> {
+    let path = git_work_dir().join(CONFIG_ROOT).join(file_name);
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
     let mut file = File::create(&path)?;
 
-    write!(file, "{contents}")?;
+    file.write_all(contents)?;
 
     Ok(())
 }
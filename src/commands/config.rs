@@ -0,0 +1,263 @@
+use anyhow::anyhow;
+use colored::Colorize;
+
+use crate::{
+    commands::{
+        help,
+        run::{get_pull_request_entry, parse_repo, read_config},
+    },
+    config_file::ConfigFile,
+    fail,
+    flags::{is_valid_flag, Flag},
+    forge::{Clients, ForgeRepo},
+    git_commands::GIT_ROOT,
+    info, success,
+    types::{CommandArgs, GitHubResponse, PullRequestEntry},
+    utils::{format_pr, pull_request_label, short_hash},
+    CONFIG_ROOT,
+};
+
+use super::help::{HELP_FLAG, VERSION_FLAG};
+
+pub static CONFIG_FLAGS: &[&Flag<'static>; 2] = &[&HELP_FLAG, &VERSION_FLAG];
+
+/// Arguments of a command which edits the config, exiting if there are none or if a flag is invalid
+fn arguments(command: &str, args: &CommandArgs) -> Vec<String> {
+    let mut arguments = vec![];
+
+    let mut no_more_flags = false;
+
+    for arg in args {
+        // After "--", each argument is interpreted literally
+        if arg == "--" {
+            no_more_flags = true;
+            continue;
+        };
+
+        if arg.starts_with('-') && !no_more_flags {
+            if !is_valid_flag(arg, CONFIG_FLAGS) {
+                fail!("Invalid flag: {arg}");
+                let _ = help(Some(command));
+                std::process::exit(1);
+            }
+
+            continue;
+        }
+
+        arguments.push(arg.clone());
+    }
+
+    if arguments.is_empty() {
+        fail!("You haven't specified anything to {command}");
+        let _ = help(Some(command));
+        std::process::exit(1);
+    }
+
+    arguments
+}
+
+/// Parses a pull request given as e.g. `#123`, `123@<hash>`, `owner/repo#123` or `<remote>:<branch>`
+fn parse_pull_request(arg: &str) -> anyhow::Result<PullRequestEntry> {
+    PullRequestEntry::parse_argument(arg).map_err(|err| {
+        anyhow!(
+            "The following argument couldn't be parsed as a pull request: {arg}\n  {err}
+  Examples of valid pull requests (with custom commit hashes supported): 1154, #500, helix-editor/tree-sitter-rust#42, https://github.com/someone/helix:feature-x, '1001@0b36296f67a80309243ea5c8892c79798c6dcf93'"
+        )
+    })
+}
+
+/// Looks up pull requests to make sure they exist before they are written to the config
+struct Validator {
+    repo: ForgeRepo,
+    clients: Clients,
+}
+
+impl Validator {
+    fn new(args: &CommandArgs) -> anyhow::Result<Self> {
+        let config = read_config(args)?;
+        let repo = parse_repo(&config)?;
+        let clients = Clients::new(&repo, config.github_token.as_deref());
+
+        Ok(Self { repo, clients })
+    }
+
+    async fn get_pull_request(
+        &self,
+        pull_request: &PullRequestEntry,
+    ) -> anyhow::Result<GitHubResponse> {
+        get_pull_request_entry(
+            &self.repo,
+            &self.clients,
+            &pull_request.number,
+            &pull_request.source,
+        )
+        .await
+        .map_err(|err| {
+            anyhow!(
+                "Could not find {}\n{err}",
+                pull_request_label(&pull_request.number)
+            )
+        })
+    }
+}
+
+/// Adds pull requests to the config, optionally pinned to a commit
+pub async fn add(args: &CommandArgs) -> anyhow::Result<()> {
+    let arguments = arguments("add", args);
+    let validator = Validator::new(args)?;
+    let mut config_file = ConfigFile::read()?;
+
+    for arg in arguments {
+        let pull_request = parse_pull_request(&arg)?;
+
+        if config_file.has_pull_request(&pull_request)? {
+            return Err(anyhow!(
+                "{} is already in pull-requests. Use {} to change its commit",
+                pull_request_label(&pull_request.number),
+                "patchy pin".bright_magenta()
+            ));
+        }
+
+        let response = validator.get_pull_request(&pull_request).await?;
+        let pr = format_pr(&pull_request.number, &response.title, &response.html_url);
+
+        config_file.add_pull_request(&pull_request)?;
+
+        match pull_request.commit {
+            Some(commit_hash) => success!(
+                "Added {pr} at commit {}",
+                short_hash(&commit_hash).bright_cyan()
+            ),
+            None => success!("Added {pr}"),
+        }
+    }
+
+    config_file.write()
+}
+
+/// Removes pull requests from the config
+pub fn remove(args: &CommandArgs) -> anyhow::Result<()> {
+    let arguments = arguments("remove", args);
+    let mut config_file = ConfigFile::read()?;
+
+    for arg in arguments {
+        let pull_request = parse_pull_request(&arg)?;
+        let label = pull_request_label(&pull_request.number);
+
+        if !config_file.remove_pull_request(&pull_request)? {
+            return Err(anyhow!("{label} is not in pull-requests"));
+        }
+
+        success!("Removed {label}");
+    }
+
+    config_file.write()
+}
+
+/// Pins pull requests to a commit, their latest one unless a commit is given
+pub async fn pin(args: &CommandArgs) -> anyhow::Result<()> {
+    let arguments = arguments("pin", args);
+    let validator = Validator::new(args)?;
+    let mut config_file = ConfigFile::read()?;
+
+    for arg in arguments {
+        let pull_request = parse_pull_request(&arg)?;
+
+        if !config_file.has_pull_request(&pull_request)? {
+            return Err(anyhow!(
+                "{} is not in pull-requests. Add it with {}",
+                pull_request_label(&pull_request.number),
+                format!("patchy add {arg}").bright_magenta()
+            ));
+        }
+
+        let response = validator.get_pull_request(&pull_request).await?;
+        let pr = format_pr(&pull_request.number, &response.title, &response.html_url);
+        let commit_hash = pull_request.commit.clone().unwrap_or(response.head.sha);
+
+        config_file.set_pull_request_commit(&pull_request, Some(&commit_hash))?;
+
+        success!(
+            "Pinned {pr} at commit {}",
+            short_hash(&commit_hash).bright_cyan()
+        );
+    }
+
+    config_file.write()
+}
+
+/// Makes pull requests follow their latest commit again
+pub fn unpin(args: &CommandArgs) -> anyhow::Result<()> {
+    let arguments = arguments("unpin", args);
+    let mut config_file = ConfigFile::read()?;
+
+    for arg in arguments {
+        let pull_request = parse_pull_request(&arg)?;
+        let label = pull_request_label(&pull_request.number);
+
+        if !config_file.has_pull_request(&pull_request)? {
+            return Err(anyhow!("{label} is not in pull-requests"));
+        }
+
+        if config_file.pull_request_commit(&pull_request)?.is_none() {
+            info!("{label} is not pinned");
+            continue;
+        }
+
+        config_file.set_pull_request_commit(&pull_request, None)?;
+
+        success!("Unpinned {label}");
+    }
+
+    config_file.write()
+}
+
+/// Adds patches from the config directory to the config
+pub fn add_patch(args: &CommandArgs) -> anyhow::Result<()> {
+    let arguments = arguments("add-patch", args);
+    let mut config_file = ConfigFile::read()?;
+
+    for arg in arguments {
+        let patch = arg.strip_suffix(".patch").unwrap_or(&arg);
+        let patch = patch
+            .strip_prefix(&format!("{CONFIG_ROOT}/"))
+            .unwrap_or(patch);
+
+        let path = GIT_ROOT.join(CONFIG_ROOT).join(format!("{patch}.patch"));
+
+        if !path.exists() {
+            return Err(anyhow!(
+                "Could not find {CONFIG_ROOT}/{patch}.patch. You can create it with {}",
+                "patchy gen-patch".bright_magenta()
+            ));
+        }
+
+        if config_file.has_patch(patch)? {
+            return Err(anyhow!("{patch} is already in patches"));
+        }
+
+        config_file.add_patch(patch)?;
+
+        success!("Added patch {}", patch.bright_cyan());
+    }
+
+    config_file.write()
+}
+
+/// Removes patches from the config, leaving their files alone
+pub fn remove_patch(args: &CommandArgs) -> anyhow::Result<()> {
+    let arguments = arguments("remove-patch", args);
+    let mut config_file = ConfigFile::read()?;
+
+    for arg in arguments {
+        let patch = arg.strip_suffix(".patch").unwrap_or(&arg);
+
+        if !config_file.remove_patch(patch)? {
+            return Err(anyhow!("{patch} is not in patches"));
+        }
+
+        success!("Removed patch {}", patch.bright_cyan());
+    }
+
+    config_file.write()
+}
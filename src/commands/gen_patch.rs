@@ -4,14 +4,15 @@ use crate::CONFIG_ROOT;
 use crate::{
     commands::help,
     fail,
-    flags::{is_valid_flag, Flag},
+    flags::{is_valid_flag, Flag, IS_JSON},
     git_commands::{is_valid_branch_name, GIT, GIT_ROOT},
+    output::{print_json, GenPatchReport, Outcome, PatchReport},
     success,
     types::CommandArgs,
     utils::normalize_commit_msg,
 };
 
-use super::help::{HELP_FLAG, VERSION_FLAG};
+use super::help::{HELP_FLAG, OUTPUT_FLAG, VERSION_FLAG};
 
 pub static GEN_PATCH_NAME_FLAG: Flag<'static> = Flag {
     short: "-n=",
@@ -19,8 +20,12 @@ pub static GEN_PATCH_NAME_FLAG: Flag<'static> = Flag {
     description: "Choose filename for the patch",
 };
 
-pub static GEN_PATCH_FLAGS: &[&Flag<'static>; 3] =
-    &[&GEN_PATCH_NAME_FLAG, &HELP_FLAG, &VERSION_FLAG];
+pub static GEN_PATCH_FLAGS: &[&Flag<'static>; 4] = &[
+    &GEN_PATCH_NAME_FLAG,
+    &OUTPUT_FLAG,
+    &HELP_FLAG,
+    &VERSION_FLAG,
+];
 
 pub fn gen_patch(args: &CommandArgs) -> anyhow::Result<()> {
     if args.is_empty() {
@@ -34,6 +39,8 @@ pub fn gen_patch(args: &CommandArgs) -> anyhow::Result<()> {
 
     let mut no_more_flags = false;
 
+    let mut report = vec![];
+
     while let Some(arg) = args.next() {
         // After "--", each argument is interpreted literally. This way, we can e.g. use filenames that are named exactly the same as flags
         if arg == "--" {
@@ -56,10 +63,17 @@ pub fn gen_patch(args: &CommandArgs) -> anyhow::Result<()> {
         let is_merge_commit = GIT(&["rev-parse", &format!("{}^2", arg)]).is_ok();
 
         if is_merge_commit {
-            fail!(
-                "Commit {} is a merge commit, which cannot be turned into a .patch file",
-                arg
+            let err = format!(
+                "Commit {arg} is a merge commit, which cannot be turned into a .patch file"
             );
+            fail!("{err}");
+            report.push(PatchReport {
+                name: arg.clone(),
+                commit: Some(arg.clone()),
+                commits: vec![],
+                outcome: Outcome::Failed,
+                error: Some(err),
+            });
 
             continue;
         }
@@ -105,6 +119,13 @@ pub fn gen_patch(args: &CommandArgs) -> anyhow::Result<()> {
         // Paths are UTF-8 encoded. If we cannot convert to UTF-8 that means it is not a valid path
         let Some(patch_file_path_str) = patch_file_path.as_os_str().to_str() else {
             fail!("Not a valid path: {patch_file_path:?}");
+            report.push(PatchReport {
+                name: patch_filename,
+                commit: Some(patch_commit_hash.clone()),
+                commits: vec![],
+                outcome: Outcome::Failed,
+                error: Some(format!("Not a valid path: {patch_file_path:?}")),
+            });
             continue;
         };
 
@@ -119,13 +140,31 @@ pub fn gen_patch(args: &CommandArgs) -> anyhow::Result<()> {
                 "Could not get patch output for patch {}\n{err}",
                 patch_commit_hash
             );
+            report.push(PatchReport {
+                name: patch_file_path_str.to_owned(),
+                commit: Some(patch_commit_hash.clone()),
+                commits: vec![],
+                outcome: Outcome::Failed,
+                error: Some(err.to_string()),
+            });
             continue;
         };
 
         success!(
             "Created patch file at {}",
             patch_file_path.to_string_lossy()
-        )
+        );
+        report.push(PatchReport {
+            name: patch_file_path_str.to_owned(),
+            commit: GIT(&["rev-parse", patch_commit_hash]).ok(),
+            commits: vec![],
+            outcome: Outcome::Created,
+            error: None,
+        });
+    }
+
+    if *IS_JSON {
+        print_json(&GenPatchReport { patches: report });
     }
 
     Ok(())
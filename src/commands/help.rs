@@ -3,8 +3,15 @@ use colored::Colorize;
 use crate::{
     commands::{
         gen_patch::GEN_PATCH_NAME_FLAG,
-        pr_fetch::{PR_FETCH_BRANCH_NAME_FLAG, PR_FETCH_CHECKOUT_FLAG, PR_FETCH_REPO_NAME_FLAG},
-        run::RUN_YES_FLAG,
+        pr_fetch::{
+            PR_FETCH_BRANCH_NAME_FLAG, PR_FETCH_CHECKOUT_FLAG, PR_FETCH_FORGE_FLAG,
+            PR_FETCH_REPO_NAME_FLAG, PR_FETCH_SSH_FLAG,
+        },
+        run::{
+            RUN_ABORT_FLAG, RUN_AUTOSTASH_FLAG, RUN_CONTINUE_FLAG, RUN_DRY_RUN_FLAG,
+            RUN_INTERACTIVE_FLAG, RUN_JOBS_FLAG, RUN_LOCKED_FLAG, RUN_PRUNE_FLAG,
+            RUN_PUSH_ANYWAY_FLAG, RUN_PUSH_FLAG, RUN_SKIP_FLAG, RUN_STRICT_FLAG, RUN_YES_FLAG,
+        },
     },
     flags::Flag,
     APP_NAME,
@@ -31,6 +38,12 @@ pub static VERBOSE_FLAG: Flag<'static> = Flag {
     description: "Increased logging information",
 };
 
+pub static OUTPUT_FLAG: Flag<'static> = Flag {
+    short: "-o=",
+    long: "--output=",
+    description: "With --output=json, print a JSON report to stdout when done, and the rest without colors to stderr",
+};
+
 pub static VERSION_FLAG: Flag<'static> = Flag {
     short: "-v",
     long: "--version",
@@ -50,10 +63,27 @@ pub fn help(command: Option<&str>) -> anyhow::Result<()> {
     let init = format_subcommand("init", "Create example config file");
     let pr_fetch = format_subcommand(
         "pr-fetch",
-        "Fetch pull request for a GitHub repository as a local branch",
+        "Fetch pull request for a GitHub, GitLab or Gitea repository as a local branch",
     );
     let gen_patch = format_subcommand("gen-patch", "Generate a .patch file from commit hashes");
     let run = format_subcommand("run", &format!("Start {APP_NAME}"));
+    let status = format_subcommand(
+        "status",
+        "Show the state of each pull request in the config, without running",
+    );
+    let add = format_subcommand("add", "Add pull requests to the config");
+    let remove = format_subcommand("remove", "Remove pull requests from the config");
+    let pin = format_subcommand("pin", "Pin pull requests in the config to a commit");
+    let unpin = format_subcommand(
+        "unpin",
+        "Make pull requests in the config use their latest commit",
+    );
+    let add_patch = format_subcommand("add-patch", "Add patches to the config");
+    let remove_patch = format_subcommand("remove-patch", "Remove patches from the config");
+    let update = format_subcommand(
+        "update",
+        "Refresh the commits recorded in the lockfile to the latest ones",
+    );
     let header = format!(
         "  {app_name} {version}
   {author}{less_than}{email}{greater_than}"
@@ -98,6 +128,157 @@ pub fn help(command: Option<&str>) -> anyhow::Result<()> {
     {HELP_FLAG}
 
     {RUN_YES_FLAG}
+
+    {RUN_JOBS_FLAG}
+
+    {RUN_DRY_RUN_FLAG}
+
+    {RUN_AUTOSTASH_FLAG}
+
+    {RUN_LOCKED_FLAG}
+
+    {RUN_PRUNE_FLAG}
+
+    {RUN_STRICT_FLAG}
+
+    {RUN_PUSH_FLAG}
+
+    {RUN_PUSH_ANYWAY_FLAG}
+
+    {RUN_INTERACTIVE_FLAG}
+
+    {RUN_CONTINUE_FLAG}
+
+    {RUN_SKIP_FLAG}
+
+    {RUN_ABORT_FLAG}
+
+    {OUTPUT_FLAG}
+",
+            );
+        }
+        Some(cmd_name @ ("add" | "remove" | "pin" | "unpin" | "add-patch" | "remove-patch")) => {
+            let this_command_name = format!("{app_name} {}", cmd_name.bright_yellow());
+
+            let (description, example, example_description) = match cmd_name {
+                "add" => (
+                    "Add pull requests to pull-requests in the config, after checking that they exist",
+                    "11745 10000@be8f264327f6ae729a0b372ef01f6fde49a78310",
+                    "Add pull request #11745, and #10000 pinned to a commit",
+                ),
+                "remove" => (
+                    "Remove pull requests from pull-requests in the config, along with the comments above them",
+                    "11745 10000",
+                    "Remove pull requests #11745 and #10000",
+                ),
+                "pin" => (
+                    "Pin pull requests in the config to a commit, their latest one if no commit is given",
+                    "11745 10000@be8f264327f6ae729a0b372ef01f6fde49a78310",
+                    "Pin #11745 to its latest commit, and #10000 to the given one",
+                ),
+                "unpin" => (
+                    "Make pull requests in the config use their latest commit again",
+                    "11745 10000",
+                    "Unpin pull requests #11745 and #10000",
+                ),
+                "add-patch" => (
+                    "Add patches from the .patchy directory to patches in the config",
+                    "my-patch",
+                    "Add the patch .patchy/my-patch.patch",
+                ),
+                _ => (
+                    "Remove patches from patches in the config, keeping their files",
+                    "my-patch",
+                    "Stop applying the patch .patchy/my-patch.patch",
+                ),
+            };
+
+            let description = format_description(description);
+            let example = format!(
+                "{}
+    {}",
+                example.bright_green(),
+                format_description(example_description)
+            );
+
+            println!(
+                "
+{header}
+        
+  Usage:
+
+    {this_command_name} {args} {flags_label}
+    {description}
+
+  Examples:
+
+    {this_command_name} {example}
+
+  Flags:
+
+    {HELP_FLAG}
+",
+            );
+        }
+        Some(cmd_name @ "status") => {
+            let this_command_name = format!("{app_name} {}", cmd_name.bright_yellow());
+
+            let description = format_description(
+                "Show whether each pull request in the config is open, merged or closed, if it changed since it was pinned, whether it can be merged and how its checks are doing",
+            );
+
+            println!(
+                "
+{header}
+        
+  Usage:
+
+    {this_command_name}
+    {description}
+
+  Flags:
+
+    {HELP_FLAG}
+",
+            );
+        }
+        Some(cmd_name @ "update") => {
+            let this_command_name = format!("{app_name} {}", cmd_name.bright_yellow());
+
+            let description = format_description(
+                "Refresh the commits recorded in .patchy/patchy.lock to the latest ones",
+            );
+
+            let example_1 = format_description(
+                "Lock the base branch and every pull request at their latest commit",
+            );
+
+            let example_2 = format!(
+                "{}
+    {}",
+                "11745 600".bright_green(),
+                format_description("Only refresh pull requests #11745 and #600")
+            );
+
+            println!(
+                "
+{header}
+        
+  Usage:
+
+    {this_command_name} {args} {flags_label}
+    {description}
+
+  Examples:
+
+    {this_command_name}
+    {example_1}
+
+    {this_command_name} {example_2}
+
+  Flags:
+
+    {HELP_FLAG}
 ",
             );
         }
@@ -154,6 +335,8 @@ pub fn help(command: Option<&str>) -> anyhow::Result<()> {
 
     {GEN_PATCH_NAME_FLAG}
 
+    {OUTPUT_FLAG}
+
     {HELP_FLAG}
 ",
             );
@@ -234,6 +417,12 @@ pub fn help(command: Option<&str>) -> anyhow::Result<()> {
 
     {PR_FETCH_REPO_NAME_FLAG}
 
+    {PR_FETCH_FORGE_FLAG}
+
+    {PR_FETCH_SSH_FLAG}
+
+    {OUTPUT_FLAG}
+
     {HELP_FLAG}
 ",
             );
@@ -253,6 +442,22 @@ pub fn help(command: Option<&str>) -> anyhow::Result<()> {
 
     {run}
 
+    {status}
+
+    {update}
+
+    {add}
+
+    {remove}
+
+    {pin}
+
+    {unpin}
+
+    {add_patch}
+
+    {remove_patch}
+
     {gen_patch} 
 
     {pr_fetch} 
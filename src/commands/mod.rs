@@ -1,11 +1,17 @@
+pub mod config;
 pub mod gen_patch;
 pub mod help;
 pub mod init;
 pub mod pr_fetch;
 pub mod run;
+pub mod status;
+pub mod update;
 
+pub use config::{add, add_patch, pin, remove, remove_patch, unpin};
 pub use gen_patch::gen_patch;
 pub use help::help;
 pub use init::init;
 pub use pr_fetch::pr_fetch;
 pub use run::run;
+pub use status::status;
+pub use update::update;
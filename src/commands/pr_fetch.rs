@@ -1,16 +1,16 @@
 use crate::commands::help;
 use crate::fail;
-use crate::flags::{is_valid_flag, Flag};
-use crate::git_commands::{
-    fetch_pull_request, is_valid_branch_name, GIT, GITHUB_REMOTE_PREFIX, GITHUB_REMOTE_SUFFIX,
-};
+use crate::flags::{is_valid_flag, Flag, IS_JSON};
+use crate::forge::{Forge, ForgeRepo};
+use crate::git_commands::{fetch_pull_request, is_valid_branch_name, GIT};
+use crate::output::{print_json, Outcome, PrFetchReport, PullRequestReport};
 use crate::success;
-use crate::types::CommandArgs;
-use crate::utils::display_link;
+use crate::types::{CommandArgs, Transport};
+use crate::utils::{format_pr, make_client};
 use anyhow::anyhow;
 use colored::Colorize;
 
-use super::help::{HELP_FLAG, VERSION_FLAG};
+use super::help::{HELP_FLAG, OUTPUT_FLAG, VERSION_FLAG};
 use super::run::parse_if_maybe_hash;
 
 /// Allow users to prefix their PRs with octothorpe, e.g. #12345 instead of 12345.
@@ -40,13 +40,28 @@ pub static PR_FETCH_REPO_NAME_FLAG: Flag<'static> = Flag {
     short: "-r=",
     long: "--repo-name=",
     description:
-        "Choose a github repository, using the `origin` remote of the current repository by default",
+        "Choose a repository such as helix-editor/helix or a URL, using the `origin` remote of the current repository by default",
+};
+
+pub static PR_FETCH_FORGE_FLAG: Flag<'static> = Flag {
+    short: "-f=",
+    long: "--forge=",
+    description: "Choose the forge hosting the repository: github, gitlab, gitea or forgejo, guessed from its host by default",
 };
 
-pub static PR_FETCH_FLAGS: &[&Flag<'static>; 5] = &[
+pub static PR_FETCH_SSH_FLAG: Flag<'static> = Flag {
+    short: "-s",
+    long: "--ssh",
+    description: "Fetch over SSH instead of HTTPS, needed for private repositories and forks",
+};
+
+pub static PR_FETCH_FLAGS: &[&Flag<'static>; 8] = &[
     &PR_FETCH_BRANCH_NAME_FLAG,
     &PR_FETCH_CHECKOUT_FLAG,
     &PR_FETCH_REPO_NAME_FLAG,
+    &PR_FETCH_FORGE_FLAG,
+    &PR_FETCH_SSH_FLAG,
+    &OUTPUT_FLAG,
     &HELP_FLAG,
     &VERSION_FLAG,
 ];
@@ -55,12 +70,20 @@ pub async fn pr_fetch(args: &CommandArgs) -> anyhow::Result<()> {
     let checkout_flag =
         args.contains(PR_FETCH_CHECKOUT_FLAG.short) || args.contains(PR_FETCH_CHECKOUT_FLAG.long);
 
+    let transport = if PR_FETCH_SSH_FLAG.is_in_args(args) {
+        Transport::Ssh
+    } else {
+        Transport::Https
+    };
+
     let mut args = args.iter().peekable();
 
     let mut pull_requests_with_maybe_custom_branch_names = vec![];
 
     let mut remote_name: Option<String> = None;
 
+    let mut forge: Option<Forge> = None;
+
     let mut no_more_flags = false;
 
     while let Some(arg) = args.next() {
@@ -75,6 +98,15 @@ pub async fn pr_fetch(args: &CommandArgs) -> anyhow::Result<()> {
             continue;
         }
 
+        if let Some(flag) = PR_FETCH_FORGE_FLAG.extract_from_arg(arg) {
+            forge = Some(Forge::from_name(&flag).ok_or_else(|| {
+                anyhow!(
+                    "Unknown forge: {flag}. Supported forges are github, gitlab, gitea and forgejo"
+                )
+            })?);
+            continue;
+        }
+
         if arg.starts_with('-') && !no_more_flags {
             if !is_valid_flag(arg, PR_FETCH_FLAGS) {
                 fail!("Invalid flag: {arg}");
@@ -116,23 +148,22 @@ pub async fn pr_fetch(args: &CommandArgs) -> anyhow::Result<()> {
         ));
     }
 
-    // The user hasn't provided a custom remote, so we're going to try `origin`
-    if remote_name.is_none() {
-        let remote = GIT(&["remote", "get-url", "origin"])?;
-        if remote.starts_with(GITHUB_REMOTE_PREFIX) && remote.ends_with(GITHUB_REMOTE_SUFFIX) {
-            let start = GITHUB_REMOTE_PREFIX.len();
-            let end = remote.len() - GITHUB_REMOTE_SUFFIX.len();
-            remote_name = remote.get(start..end).map(|remote| remote.into());
-        };
-    }
-
-    let Some(remote_name) = remote_name else {
-        return Err(anyhow!(
-            "Could not get the remote, it should be in the form e.g. helix-editor/helix.",
-        ));
+    let repo = match remote_name {
+        Some(remote_name) => ForgeRepo::parse(&remote_name, forge)?,
+        // The user hasn't provided a custom remote, so we're going to try `origin`
+        None => {
+            let remote = GIT(&["remote", "get-url", "origin"])?;
+            ForgeRepo::from_remote_url(&remote, forge).map_err(|err| {
+                anyhow!(
+                    "Could not get the remote, it should be in the form e.g. helix-editor/helix.\n{err}"
+                )
+            })?
+        }
     };
 
-    let client = reqwest::Client::new();
+    let client = make_client(repo.forge, repo.forge.token(None).as_deref())?;
+
+    let mut report = vec![];
 
     for (i, (pull_request, maybe_custom_branch_name, hash)) in
         pull_requests_with_maybe_custom_branch_names
@@ -140,27 +171,20 @@ pub async fn pr_fetch(args: &CommandArgs) -> anyhow::Result<()> {
             .enumerate()
     {
         match fetch_pull_request(
-            &remote_name,
+            &repo,
             pull_request,
             &client,
             maybe_custom_branch_name.as_deref(),
             hash,
+            transport,
         )
         .await
         {
             Ok((response, info)) => {
                 success!(
-                    "Fetched pull request {} available at branch {}{}",
-                    display_link(
-                        &format!(
-                            "{}{}{}{}",
-                            "#".bright_blue(),
-                            pull_request.bright_blue(),
-                            " ".bright_blue(),
-                            response.title.bright_blue().italic()
-                        ),
-                        &response.html_url
-                    ),
+                    "Fetched {} {} available at branch {}{}",
+                    repo.forge.pull_request_name(),
+                    format_pr(pull_request, &response.title, &response.html_url),
                     info.branch.local_branch_name.bright_cyan(),
                     hash.clone()
                         .map(|commit_hash| format!(", at commit {}", commit_hash.bright_yellow()))
@@ -170,6 +194,17 @@ pub async fn pr_fetch(args: &CommandArgs) -> anyhow::Result<()> {
                 // Attempt to cleanup after ourselves
                 let _ = GIT(&["remote", "remove", &info.remote.local_remote_alias]);
 
+                report.push(PullRequestReport {
+                    number: pull_request.clone(),
+                    title: Some(response.title.clone()),
+                    url: Some(response.html_url.clone()),
+                    commit: GIT(&["rev-parse", &info.branch.local_branch_name]).ok(),
+                    strategy: None,
+                    branch: Some(info.branch.local_branch_name.clone()),
+                    outcome: Outcome::Fetched,
+                    error: None,
+                });
+
                 // If user uses --checkout flag, we're going to checkout the first PR only
                 if i == 0 && checkout_flag {
                     if let Err(cant_checkout) = GIT(&["checkout", &info.branch.local_branch_name]) {
@@ -187,10 +222,26 @@ pub async fn pr_fetch(args: &CommandArgs) -> anyhow::Result<()> {
             }
             Err(err) => {
                 fail!("{err}");
+                report.push(PullRequestReport {
+                    number: pull_request.clone(),
+                    title: None,
+                    url: None,
+                    commit: hash.clone(),
+                    strategy: None,
+                    branch: maybe_custom_branch_name.clone(),
+                    outcome: Outcome::Failed,
+                    error: Some(err.to_string()),
+                });
                 continue;
             }
         };
     }
 
+    if *IS_JSON {
+        print_json(&PrFetchReport {
+            pull_requests: report,
+        });
+    }
+
     Ok(())
 }
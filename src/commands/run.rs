@@ -1,20 +1,37 @@
-use std::fs;
+use std::{fs, sync::Arc};
 
 use anyhow::anyhow;
 use colored::Colorize;
+use tempfile::TempDir;
+use tokio::{sync::Semaphore, task::JoinHandle};
 
 use crate::{
     backup::{backup_files, restore_backup},
-    commands::{init, pr_fetch::ignore_octothorpe},
+    commands::init,
+    config_file::ConfigFile,
     confirm_prompt, fail,
-    flags::Flag,
+    flags::{Flag, IS_JSON},
+    forge::{Clients, ForgeRepo},
     git_commands::{
-        add_remote_branch, checkout_from_remote, clean_up_remote, fetch_pull_request,
-        merge_pull_request, GIT, GIT_ROOT,
+        add_remote_branch, apply_patch, cherry_pick_in_progress, clean_up_remote,
+        continue_cherry_pick, create_throwaway_clone, fetch_pull_request_branch, get_branch,
+        git_work_dir, merge_octopus, merge_pull_request, push_with_lease, set_git_work_dir,
+        uncommitted_changes, unmerged_files, Autostash, TemporaryWorktree, GIT, GIT_ROOT,
     },
-    info, success, trace,
-    types::{Branch, BranchAndRemote, CommandArgs, Configuration, Remote},
-    utils::{display_link, with_uuid},
+    human, info,
+    lockfile::{LockedBase, LockedPullRequest, Lockfile, LOCKFILE},
+    output::{print_json, Outcome, PatchReport, PullRequestReport, RunReport},
+    patch_cache::{
+        download_patches, patch_cache, remove_unused_patches, DownloadedPatches, PATCH_CACHE,
+    },
+    rerere::{load_resolutions, save_resolutions, RERERE_CACHE},
+    state::{Failed, Fetched, PausedMerge, Report, RunState},
+    success, trace,
+    types::{
+        Branch, BranchAndRemote, CommandArgs, Configuration, GitHubResponse, MergeStrategy,
+        PatchFailure, PatchSource, PullRequestEntry, PullRequestState, Remote, Source,
+    },
+    utils::{display_link, format_pr, pull_request_label, with_uuid},
     APP_NAME, CONFIG_FILE, CONFIG_ROOT, INDENT,
 };
 
@@ -24,6 +41,83 @@ pub static RUN_YES_FLAG: Flag<'static> = Flag {
     description: "Do not prompt when overwriting local-branch specified in the config",
 };
 
+pub static RUN_JOBS_FLAG: Flag<'static> = Flag {
+    short: "-j=",
+    long: "--jobs=",
+    description: "Maximum number of pull requests to fetch at the same time, 8 by default",
+};
+
+pub static RUN_DRY_RUN_FLAG: Flag<'static> = Flag {
+    short: "-d",
+    long: "--dry-run",
+    description:
+        "Report which pull requests and patches would apply, without changing any branch or remote",
+};
+
+pub static RUN_AUTOSTASH_FLAG: Flag<'static> = Flag {
+    short: "-a",
+    long: "--autostash",
+    description: "Stash uncommitted changes before running and restore them afterwards, instead of refusing to run",
+};
+
+pub static RUN_LOCKED_FLAG: Flag<'static> = Flag {
+    short: "-l",
+    long: "--locked",
+    description: "Use the exact commits recorded in .patchy/patchy.lock instead of the latest ones",
+};
+
+pub static RUN_PRUNE_FLAG: Flag<'static> = Flag {
+    short: "-p",
+    long: "--prune",
+    description: "Remove pull requests which were merged or closed from the config",
+};
+
+pub static RUN_INTERACTIVE_FLAG: Flag<'static> = Flag {
+    short: "-i",
+    long: "--interactive",
+    description: "Pause when a pull request has merge conflicts so you can resolve them, instead of skipping it",
+};
+
+pub static RUN_STRICT_FLAG: Flag<'static> = Flag {
+    short: "-s",
+    long: "--strict",
+    description: "Stop at the first pull request or patch which can't be merged or applied, leaving local-branch as it was",
+};
+
+pub static RUN_PUSH_FLAG: Flag<'static> = Flag {
+    short: "",
+    long: "--push",
+    description: "Push the result to push-remote with --force-with-lease, unless pull requests or patches were left out of it",
+};
+
+pub static RUN_PUSH_ANYWAY_FLAG: Flag<'static> = Flag {
+    short: "",
+    long: "--push-anyway",
+    description:
+        "Like --push, but also push when pull requests or patches were left out of the result",
+};
+
+pub static RUN_CONTINUE_FLAG: Flag<'static> = Flag {
+    short: "-C",
+    long: "--continue",
+    description: "Resume a paused run once its merge conflicts are resolved and staged",
+};
+
+pub static RUN_SKIP_FLAG: Flag<'static> = Flag {
+    short: "",
+    long: "--skip",
+    description: "Resume a paused run, leaving out the pull request it stopped at",
+};
+
+pub static RUN_ABORT_FLAG: Flag<'static> = Flag {
+    short: "",
+    long: "--abort",
+    description: "Give up on a paused run, leaving local-branch as it was",
+};
+
+/// How many pull requests are fetched at the same time when neither `--jobs` nor `jobs` is set
+pub static DEFAULT_JOBS: usize = 8;
+
 /// Parses user inputs of the form "(<anything>)+ @ <commit-hash>"
 ///
 /// Returns the user's input but also the commit hash if it exists
@@ -43,13 +137,519 @@ pub fn parse_if_maybe_hash(input: &str, syntax: &str) -> (String, Option<String>
     }
 }
 
-pub async fn run(args: &CommandArgs) -> anyhow::Result<()> {
-    println!();
+/// The branch is `None` for pull requests that are left out since they are closed
+type PullRequestFetch = JoinHandle<anyhow::Result<(GitHubResponse, Option<BranchAndRemote>)>>;
 
-    let config_path = GIT_ROOT.join(CONFIG_ROOT);
-    let has_yes_flag = RUN_YES_FLAG.is_in_args(args);
+/// What happens when a pull request can't be merged
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum OnFailure {
+    /// Leave it out and carry on with the rest
+    Skip,
+    /// Leave it out, unless it has merge conflicts which the user can resolve before carrying on
+    Pause,
+    /// Stop merging, for `--strict`
+    Stop,
+}
+
+/// How to resume a run which was paused by `--interactive`
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Resume {
+    Continue,
+    Skip,
+    Abort,
+}
+
+/// Starts fetching every pull request in the background, at most `jobs` at a time
+///
+/// The fetches are returned in the same order as the pull requests, so that they can be merged in that order
+/// Pull requests which are merged or closed are not fetched. With a `locked` lockfile, each pull request
+/// is fetched at the commit recorded in it, and left out if it was closed when the lockfile was written
+fn spawn_pull_request_fetches(
+    config: &Configuration,
+    pull_requests: &[&PullRequestEntry],
+    repo: &ForgeRepo,
+    clients: &Clients,
+    jobs: usize,
+    locked: Option<&Lockfile>,
+) -> Vec<(String, PullRequestFetch)> {
+    let semaphore = Arc::new(Semaphore::new(jobs));
+
+    pull_requests
+        .iter()
+        .filter(|pull_request| pull_request.enabled)
+        .map(|pull_request| {
+            let locked_pull_request =
+                locked.map(|lockfile| lockfile.pull_request(&pull_request.number));
+            let locked_commit = locked_pull_request
+                .flatten()
+                .map(|locked| locked.commit.clone());
+            let is_locked = locked_commit.is_some();
+            let commit_hash = locked_commit.or_else(|| pull_request.commit.clone());
+            let locked_closed =
+                locked_pull_request.map(|locked| locked.is_some_and(|locked| locked.closed));
+            let branch = pull_request.branch.clone();
+
+            let semaphore = Arc::clone(&semaphore);
+            let clients = clients.clone();
+            let repo = repo.clone();
+            let transport = config.transport;
+            let number = pull_request.number.clone();
+            let source = pull_request.source.clone();
+
+            let fetch = tokio::spawn(async move {
+                let _permit = semaphore.acquire_owned().await?;
+
+                // The branch is deleted once the pull request is merged, so never reuse one of the user's
+                if let Some(branch) = &branch {
+                    if GIT(&[
+                        "rev-parse",
+                        "--verify",
+                        "--quiet",
+                        &format!("refs/heads/{branch}"),
+                    ])
+                    .is_ok()
+                    {
+                        return Err(anyhow!(
+                            "Could not fetch {} into branch {branch} since it already exists\n",
+                            pull_request_label(&number)
+                        ));
+                    }
+                }
+
+                let response = get_pull_request_entry(&repo, &clients, &number, &source).await?;
+
+                if locked_closed.unwrap_or(response.state == PullRequestState::Closed) {
+                    return Ok((response, None));
+                }
+
+                // A branch which has no pull request has no ref to fall back on
+                let pull_request_ref = match &source {
+                    Source::PullRequest => Some((repo.clone(), number.as_str())),
+                    Source::OtherRepo {
+                        repo: other_repo,
+                        number,
+                    } => Some((repo.other_repo(other_repo)?, number.as_str())),
+                    Source::Branch { .. } => None,
+                }
+                .map(|(repo, number)| {
+                    (
+                        repo.repository_url(transport),
+                        repo.pull_request_ref(number),
+                    )
+                });
+
+                let info = fetch_pull_request_branch(
+                    &response,
+                    &number,
+                    branch.as_deref(),
+                    &commit_hash,
+                    transport,
+                    pull_request_ref,
+                )
+                .await
+                .map_err(|err| {
+                    if is_locked {
+                        anyhow!(
+                            "{err}\nTo merge its latest commit instead of the one in {CONFIG_ROOT}/{LOCKFILE}, refresh it with {} {number}",
+                            format!("{APP_NAME} update").bright_magenta()
+                        )
+                    } else {
+                        err
+                    }
+                })?;
+
+                Ok((response, Some(info)))
+            });
+
+            (pull_request.number.clone(), fetch)
+        })
+        .collect()
+}
+
+/// Waits for a pull request to be fetched
+async fn wait_for_fetch(
+    fetch: PullRequestFetch,
+) -> anyhow::Result<(GitHubResponse, Option<BranchAndRemote>)> {
+    match fetch.await {
+        Ok(fetched) => fetched,
+        Err(err) => Err(anyhow!(err)),
+    }
+}
+
+/// Waits for pull requests which won't be merged, and removes the branches and remotes they were fetched into
+async fn discard_fetches(fetches: impl IntoIterator<Item = PullRequestFetch>) {
+    for fetch in fetches {
+        if let Ok((_, Some(info))) = wait_for_fetch(fetch).await {
+            let _ = clean_up_remote(
+                &info.remote.local_remote_alias,
+                &info.branch.local_branch_name,
+            );
+        }
+    }
+}
+
+/// Waits for a pull request to be fetched and records its commit in the `lockfile`
+///
+/// Returns its branch, or `None` if it is left out of the run because it couldn't be fetched or is closed
+async fn receive_pull_request(
+    repo: &ForgeRepo,
+    pull_request: &str,
+    fetch: PullRequestFetch,
+    report: &mut Report,
+    failed: &mut Failed,
+    lockfile: &mut Lockfile,
+) -> anyhow::Result<Option<(GitHubResponse, BranchAndRemote)>> {
+    let (response, info) = match wait_for_fetch(fetch).await {
+        Ok(fetched) => fetched,
+        Err(err) => {
+            fail!("Could not fetch branch from remote\n{err}");
+            report.push(Err(format!(
+                "{} could not be fetched",
+                pull_request_label(pull_request)
+            )));
+            failed.insert(pull_request.into(), "could not be fetched".into());
+            return Ok(None);
+        }
+    };
+
+    let pr = format_pr(pull_request, &response.title, &response.html_url);
+
+    let Some(info) = info else {
+        lockfile.lock_pull_request(LockedPullRequest {
+            number: pull_request.into(),
+            commit: response.head.sha.clone(),
+            title: response.title.clone(),
+            url: response.html_url.clone(),
+            closed: true,
+        });
+
+        if response.merged {
+            info!(
+                "Skipping {} {pr} since it was merged",
+                repo.forge.pull_request_name()
+            );
+            report.push(Ok(format!("{pr} is skipped since it was merged")));
+        } else {
+            fail!(
+                "Skipping {} {pr} since it was closed without being merged",
+                repo.forge.pull_request_name()
+            );
+            report.push(Err(format!("{pr} is skipped since it was closed")));
+            failed.insert(
+                pull_request.into(),
+                "was closed without being merged".into(),
+            );
+        }
+
+        return Ok(None);
+    };
+
+    lockfile.lock_pull_request(LockedPullRequest {
+        number: pull_request.into(),
+        commit: GIT(&["rev-parse", &info.branch.local_branch_name])?,
+        title: response.title.clone(),
+        url: response.html_url.clone(),
+        closed: false,
+    });
+
+    Ok(Some((response, info)))
+}
+
+/// Merges pull requests which use the octopus strategy with a single merge commit
+fn merge_octopus_pull_requests(
+    repo: &ForgeRepo,
+    group: Vec<(String, GitHubResponse, BranchAndRemote)>,
+    report: &mut Report,
+    failed: &mut Failed,
+) -> anyhow::Result<()> {
+    let prs: Vec<_> = group
+        .iter()
+        .map(|(pull_request, response, _)| {
+            format_pr(pull_request, &response.title, &response.html_url)
+        })
+        .collect();
+
+    let branches: Vec<_> = group
+        .iter()
+        .map(|(_, _, info)| info.branch.local_branch_name.as_str())
+        .collect();
+
+    let merged = merge_octopus(&branches);
+
+    for (_, _, info) in &group {
+        clean_up_remote(
+            &info.remote.local_remote_alias,
+            &info.branch.local_branch_name,
+        )?;
+    }
+
+    match merged {
+        Ok(()) => {
+            success!(
+                "Merged {} {} using octopus",
+                repo.forge.pull_request_name(),
+                prs.join(", ")
+            );
+            report.extend(
+                prs.iter()
+                    .map(|pr| Ok(format!("{pr} merges cleanly using octopus"))),
+            );
+        }
+        Err(err) => {
+            fail!(
+                "Could not merge {} {} in a single octopus merge, since they conflict. \
+                Give them another strategy to merge them one at a time\n{err}",
+                repo.forge.pull_request_name(),
+                prs.join(", ")
+            );
+            report.extend(
+                prs.iter()
+                    .map(|pr| Err(format!("{pr} has merge conflicts using octopus"))),
+            );
+            for (pull_request, _, _) in group {
+                failed.insert(pull_request, "has merge conflicts using octopus".into());
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Leaves out a pull request if one of the pull requests it depends on wasn't merged, which
+/// means the pull requests that depend on it are left out too
+///
+/// Returns whether it was left out
+fn skip_if_dependency_failed(
+    config: &Configuration,
+    repo: &ForgeRepo,
+    (pull_request, response, info): (&str, &GitHubResponse, &BranchAndRemote),
+    report: &mut Report,
+    failed: &mut Failed,
+) -> anyhow::Result<bool> {
+    let Some((dependency, why)) = config
+        .pull_requests
+        .iter()
+        .filter(|entry| entry.number == pull_request)
+        .flat_map(|entry| &entry.depends_on)
+        .find_map(|dependency| failed.get(dependency).map(|why| (dependency, why)))
+    else {
+        return Ok(false);
+    };
+
+    let reason = format!("depends on {}, which {why}", pull_request_label(dependency));
+    let pr = format_pr(pull_request, &response.title, &response.html_url);
+
+    fail!(
+        "Skipping {} {pr} since it {reason}",
+        repo.forge.pull_request_name()
+    );
+    report.push(Err(format!("{pr} is skipped since it {reason}")));
+
+    clean_up_remote(
+        &info.remote.local_remote_alias,
+        &info.branch.local_branch_name,
+    )?;
+
+    failed.insert(pull_request.into(), reason);
+
+    Ok(true)
+}
+
+/// Merges each pull request in order, as soon as it has been fetched
+///
+/// With [`OnFailure::Pause`] the merges stop at the first one with conflicts, which is returned
+/// along with the pull requests that weren't merged yet. With [`OnFailure::Stop`] they stop at the first
+/// pull request which can't be merged
+///
+/// Pull requests which depend on one that wasn't merged are left out, the reasons are kept in `failed`
+///
+/// The commit of every pull request that could be fetched is recorded in the `lockfile`
+async fn merge_pull_requests(
+    config: &Configuration,
+    repo: &ForgeRepo,
+    fetches: Vec<(String, PullRequestFetch)>,
+    on_failure: OnFailure,
+    report: &mut Report,
+    failed: &mut Failed,
+    lockfile: &mut Lockfile,
+) -> anyhow::Result<Option<(PausedMerge, Vec<(String, Fetched)>)>> {
+    for entry in config.pull_requests.iter().filter(|entry| !entry.enabled) {
+        failed.insert(entry.number.clone(), "is disabled".into());
+    }
 
-    let config_file_path = config_path.join(CONFIG_FILE);
+    let mut fetches = fetches.into_iter().peekable();
+
+    // Fetches run in the background while we merge, but merges happen one at a time in the order
+    // of the config, with the pull requests each one depends on before it
+    while let Some((pull_request, fetch)) = fetches.next() {
+        if on_failure == OnFailure::Stop && report.iter().any(Result::is_err) {
+            discard_fetches([fetch].into_iter().chain(fetches.map(|(_, fetch)| fetch))).await;
+            break;
+        }
+
+        let Some((response, info)) =
+            receive_pull_request(repo, &pull_request, fetch, report, failed, lockfile).await?
+        else {
+            continue;
+        };
+
+        if skip_if_dependency_failed(
+            config,
+            repo,
+            (&pull_request, &response, &info),
+            report,
+            failed,
+        )? {
+            continue;
+        }
+
+        let strategy = config.merge_strategy(&pull_request);
+
+        let (pull_request, response, info) = if strategy == MergeStrategy::Octopus {
+            let mut group = vec![(pull_request, response, info)];
+
+            while let Some((pull_request, fetch)) =
+                fetches.next_if(|(next, _)| config.merge_strategy(next) == MergeStrategy::Octopus)
+            {
+                let Some((response, info)) =
+                    receive_pull_request(repo, &pull_request, fetch, report, failed, lockfile)
+                        .await?
+                else {
+                    continue;
+                };
+
+                if !skip_if_dependency_failed(
+                    config,
+                    repo,
+                    (&pull_request, &response, &info),
+                    report,
+                    failed,
+                )? {
+                    group.push((pull_request, response, info));
+                }
+            }
+
+            // A single pull request is merged on its own, so that its conflicts can be resolved
+            if group.len() > 1 {
+                merge_octopus_pull_requests(repo, group, report, failed)?;
+                continue;
+            }
+
+            let Some(single) = group.pop() else {
+                continue;
+            };
+
+            single
+        } else {
+            (pull_request, response, info)
+        };
+
+        let pr = format_pr(&pull_request, &response.title, &response.html_url);
+
+        match merge_pull_request(
+            &info,
+            &pull_request,
+            &response.title,
+            &response.html_url,
+            strategy,
+            on_failure == OnFailure::Pause,
+        )
+        .await
+        {
+            Ok(()) => {
+                success!(
+                    "Merged {} {pr} using {strategy}",
+                    repo.forge.pull_request_name()
+                );
+                report.push(Ok(format!("{pr} merges cleanly using {strategy}")));
+            }
+            Err(_) if on_failure == OnFailure::Pause && !unmerged_files()?.is_empty() => {
+                let mut remaining = vec![];
+
+                for (pull_request, fetch) in fetches {
+                    let fetched = wait_for_fetch(fetch).await.map_err(|err| err.to_string());
+                    remaining.push((pull_request, fetched));
+                }
+
+                let paused = PausedMerge {
+                    pull_request,
+                    response,
+                    info,
+                };
+
+                return Ok(Some((paused, remaining)));
+            }
+            Err(err) => {
+                fail!("{err}");
+                report.push(Err(format!("{pr} has merge conflicts using {strategy}")));
+                failed.insert(
+                    pull_request,
+                    format!("has merge conflicts using {strategy}"),
+                );
+            }
+        }
+    }
+
+    Ok(None)
+}
+
+/// Keeps the worktree with the conflicts around and saves everything needed to resume the run
+fn pause_run(
+    repo: &ForgeRepo,
+    worktree: TemporaryWorktree,
+    base: BranchAndRemote,
+    (paused, remaining): (PausedMerge, Vec<(String, Fetched)>),
+    report: Report,
+    failed: Failed,
+    lockfile: Lockfile,
+) -> anyhow::Result<()> {
+    let conflicts = unmerged_files()?;
+    let pr = format_pr(
+        &paused.pull_request,
+        &paused.response.title,
+        &paused.response.html_url,
+    );
+    let worktree = worktree.pause();
+
+    RunState {
+        worktree: worktree.clone(),
+        base,
+        paused,
+        remaining,
+        report,
+        failed,
+        lockfile,
+    }
+    .save()?;
+
+    fail!(
+        "Paused at {} {pr} since it has merge conflicts in:\n{}",
+        repo.forge.pull_request_name(),
+        conflicts
+            .iter()
+            .map(|file| format!("{INDENT}{INDENT}{}", file.bright_yellow()))
+            .collect::<Vec<_>>()
+            .join("\n")
+    );
+
+    let command = |flag: &Flag| format!("{APP_NAME} run {}", flag.long).bright_magenta();
+
+    human!(
+        "\n{INDENT}Resolve the conflicts in {}\n{INDENT}and stage them with {}, then run one of:\n\n{INDENT}{INDENT}{}  to merge it and carry on\n{INDENT}{INDENT}{}      to leave it out\n{INDENT}{INDENT}{}     to stop, leaving your branches as they were\n",
+        worktree.to_string_lossy().bright_cyan(),
+        "git add".bright_blue(),
+        command(&RUN_CONTINUE_FLAG),
+        command(&RUN_SKIP_FLAG),
+        command(&RUN_ABORT_FLAG),
+    );
+
+    Ok(())
+}
+
+/// Reads and parses the config file, offering to create it if it doesn't exist
+pub fn read_config(args: &CommandArgs) -> anyhow::Result<Configuration> {
+    let has_yes_flag = RUN_YES_FLAG.is_in_args(args);
+    let config_file_path = GIT_ROOT.join(CONFIG_ROOT).join(CONFIG_FILE);
 
     let Ok(config_raw) = fs::read_to_string(config_file_path.clone()) else {
         fail!("Could not find configuration file at {CONFIG_ROOT}/{CONFIG_FILE}");
@@ -81,12 +681,96 @@ pub async fn run(args: &CommandArgs) -> anyhow::Result<()> {
 
     trace!("Using configuration file {config_file_path:?}");
 
-    let config = toml::from_str::<Configuration>(&config_raw).map_err(|err| {
+    toml::from_str::<Configuration>(&config_raw).map_err(|err| {
         anyhow!("Could not parse `{CONFIG_ROOT}/{CONFIG_FILE}` configuration file:\n{err}")
-    })?;
+    })
+}
 
-    let (remote_branch, commit_hash) = parse_if_maybe_hash(&config.remote_branch, " @ ");
+/// Makes sure every patch file in the config exists, and points out the `.patch` files in the
+/// config directory which aren't in the config, since those are never applied
+fn check_patch_files(config: &Configuration) -> anyhow::Result<()> {
+    let config_path = GIT_ROOT.join(CONFIG_ROOT);
+
+    let listed: Vec<&str> = config
+        .patches
+        .iter()
+        .flatten()
+        .filter_map(|patch| match &patch.source {
+            PatchSource::File(name) => Some(name.as_str()),
+            PatchSource::Url { .. } => None,
+        })
+        .collect();
 
+    let missing: Vec<_> = listed
+        .iter()
+        .map(|name| format!("{CONFIG_ROOT}/{name}.patch"))
+        .filter(|path| !GIT_ROOT.join(path).is_file())
+        .collect();
+
+    if !missing.is_empty() {
+        return Err(anyhow!(
+            "Could not find {}, listed in `patches` of {CONFIG_ROOT}/{CONFIG_FILE}. You can create patches with {}",
+            missing.join(", "),
+            "patchy gen-patch".bright_magenta()
+        ));
+    }
+
+    let mut unlisted: Vec<_> = fs::read_dir(&config_path)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter_map(|entry| {
+            entry
+                .file_name()
+                .to_str()
+                .and_then(|file_name| file_name.strip_suffix(".patch"))
+                .filter(|name| !listed.contains(name))
+                .map(String::from)
+        })
+        .collect();
+    unlisted.sort();
+
+    for name in unlisted {
+        info!(
+            "Leaving out {CONFIG_ROOT}/{name}.patch since it is not in `patches`. Add it with {}",
+            format!("patchy add-patch {name}").bright_magenta()
+        );
+    }
+
+    Ok(())
+}
+
+/// Looks up an entry of `pull-requests`, wherever it is from
+pub async fn get_pull_request_entry(
+    repo: &ForgeRepo,
+    clients: &Clients,
+    number: &str,
+    source: &Source,
+) -> anyhow::Result<GitHubResponse> {
+    let (repo, pull_request) = match source {
+        Source::PullRequest => (repo.clone(), number),
+        Source::OtherRepo {
+            repo: other_repo,
+            number,
+        } => (repo.other_repo(other_repo)?, number.as_str()),
+        Source::Branch { remote, branch } => {
+            let (remote, branch) = (remote.clone(), branch.clone());
+            return tokio::task::spawn_blocking(move || get_branch(&remote, &branch)).await?;
+        }
+    };
+
+    repo.get_pull_request(&clients.get(&repo)?, pull_request)
+        .await
+        .map_err(|err| {
+            anyhow!(
+                "Could not fetch {} {}\n{err}\n",
+                repo.forge.pull_request_name(),
+                pull_request_label(number)
+            )
+        })
+}
+
+pub fn parse_repo(config: &Configuration) -> anyhow::Result<ForgeRepo> {
     if config.repo.is_empty() {
         return Err(anyhow::anyhow!(
             r#"You haven't specified a `repo` in your config, which can be for example:
@@ -97,16 +781,244 @@ pub async fn run(args: &CommandArgs) -> anyhow::Result<()> {
         ));
     }
 
-    let config_files = fs::read_dir(&config_path).map_err(|err| {
+    ForgeRepo::parse(&config.repo, config.forge)
+}
+
+/// Refuses to run with uncommitted changes, or stashes them if `--autostash` was passed
+///
+/// The stashed changes are restored once the returned value goes out of scope
+fn stash_changes(args: &CommandArgs) -> anyhow::Result<Option<Autostash>> {
+    let changes = uncommitted_changes()?;
+
+    if changes.is_empty() {
+        Ok(None)
+    } else if RUN_AUTOSTASH_FLAG.is_in_args(args) {
+        info!("Stashing {} uncommitted changes", changes.len());
+        Ok(Some(Autostash::push()?))
+    } else {
+        Err(anyhow!(
+            "You have uncommitted changes:\n{}\nCommit or stash them, or use the {} flag to stash them for the duration of the run",
+            changes
+                .iter()
+                .map(|change| format!("{INDENT}{INDENT}{}", change.bright_yellow()))
+                .collect::<Vec<_>>()
+                .join("\n"),
+            RUN_AUTOSTASH_FLAG.long.bright_magenta()
+        ))
+    }
+}
+
+/// Reads the lockfile for `--locked`, making sure it has a commit for the base and every pull request
+fn read_locked(config: &Configuration, remote_branch: &str) -> anyhow::Result<Lockfile> {
+    let update = format!("{APP_NAME} update").bright_magenta();
+
+    let lockfile = Lockfile::read()?.ok_or_else(|| {
         anyhow!(
-            "Could not read files in directory {:?}\n{err}",
-            &config_path
+            "{} was passed, but there is no {CONFIG_ROOT}/{LOCKFILE}. Create it with {update}",
+            RUN_LOCKED_FLAG.long
         )
     })?;
 
-    let backed_up_files = backup_files(config_files).map_err(|err| {
-        anyhow!("Could not create backups for configuration files, aborting.\n{err}")
-    })?;
+    match &lockfile.base {
+        Some(base) if base.branch == remote_branch => (),
+        _ => {
+            return Err(anyhow!(
+                "{CONFIG_ROOT}/{LOCKFILE} does not have a commit for branch {remote_branch}, refresh it with {update}"
+            ))
+        }
+    }
+
+    for pull_request in config.pull_requests.iter().filter(|entry| entry.enabled) {
+        let pull_request = &pull_request.number;
+
+        if lockfile.pull_request(pull_request).is_none() {
+            return Err(anyhow!(
+                "{} is not in {CONFIG_ROOT}/{LOCKFILE}, add it with {update} {pull_request}",
+                pull_request_label(pull_request)
+            ));
+        }
+    }
+
+    Ok(lockfile)
+}
+
+/// Removes the pull requests which were left out since they are closed from the config and the lockfile
+fn prune_closed(config: &Configuration, lockfile: &mut Lockfile) -> anyhow::Result<()> {
+    if !lockfile.pull_requests.iter().any(|locked| locked.closed) {
+        return Ok(());
+    }
+
+    let mut config_file = ConfigFile::read()?;
+
+    for locked in lockfile.pull_requests.iter().filter(|locked| locked.closed) {
+        let Some(entry) = config
+            .pull_requests
+            .iter()
+            .find(|entry| entry.number == locked.number)
+        else {
+            continue;
+        };
+
+        if config_file.remove_pull_request(entry)? {
+            success!(
+                "Removed {} from {}",
+                format_pr(&locked.number, &locked.title, &locked.url),
+                "pull-requests".bright_cyan()
+            );
+        }
+    }
+
+    config_file.write()?;
+    lockfile.pull_requests.retain(|locked| !locked.closed);
+
+    Ok(())
+}
+
+fn save_new_resolutions() {
+    match save_resolutions() {
+        Ok(0) => (),
+        Ok(saved) => info!(
+            "Saved {saved} new conflict resolutions to {}, commit them to keep them",
+            format!("{CONFIG_ROOT}/{RERERE_CACHE}").bright_cyan()
+        ),
+        Err(err) => fail!("Could not save conflict resolutions\n{err}"),
+    }
+}
+
+pub async fn run(args: &CommandArgs) -> anyhow::Result<()> {
+    human!();
+
+    let has_dry_run_flag = RUN_DRY_RUN_FLAG.is_in_args(args);
+    let has_interactive_flag = RUN_INTERACTIVE_FLAG.is_in_args(args);
+    let has_locked_flag = RUN_LOCKED_FLAG.is_in_args(args);
+    let has_prune_flag = RUN_PRUNE_FLAG.is_in_args(args);
+    let has_strict_flag = RUN_STRICT_FLAG.is_in_args(args);
+    let has_push_flag = RUN_PUSH_FLAG.is_in_args(args) || RUN_PUSH_ANYWAY_FLAG.is_in_args(args);
+    let jobs_flag = args
+        .iter()
+        .find_map(|arg| RUN_JOBS_FLAG.extract_from_arg(arg));
+
+    let resume = [
+        (Resume::Continue, &RUN_CONTINUE_FLAG),
+        (Resume::Skip, &RUN_SKIP_FLAG),
+        (Resume::Abort, &RUN_ABORT_FLAG),
+    ]
+    .into_iter()
+    .filter(|(_, flag)| flag.is_in_args(args))
+    .collect::<Vec<_>>();
+
+    match resume.as_slice() {
+        [] => (),
+        [(resume, _)] => return resume_run(args, *resume).await,
+        _ => {
+            return Err(anyhow!(
+                "Only one of {}, {} and {} can be used at a time",
+                RUN_CONTINUE_FLAG.long,
+                RUN_SKIP_FLAG.long,
+                RUN_ABORT_FLAG.long
+            ))
+        }
+    }
+
+    if has_dry_run_flag && has_interactive_flag {
+        return Err(anyhow!(
+            "{} can't be used with {}, since a dry run doesn't stop for merge conflicts",
+            RUN_INTERACTIVE_FLAG.long,
+            RUN_DRY_RUN_FLAG.long
+        ));
+    }
+
+    if has_strict_flag && has_interactive_flag {
+        return Err(anyhow!(
+            "{} can't be used with {}, since the run would stop at merge conflicts instead of pausing",
+            RUN_STRICT_FLAG.long,
+            RUN_INTERACTIVE_FLAG.long
+        ));
+    }
+
+    if RunState::load()?.is_some() {
+        return Err(anyhow!(
+            "A run is paused at a merge conflict. Use {}, {} or {} first",
+            RUN_CONTINUE_FLAG.long.bright_magenta(),
+            RUN_SKIP_FLAG.long.bright_magenta(),
+            RUN_ABORT_FLAG.long.bright_magenta()
+        ));
+    }
+
+    let config = read_config(args)?;
+    let pull_requests = config.ordered_pull_requests()?;
+    check_patch_files(&config)?;
+
+    // Better to find out the result can't be pushed before doing all the work
+    if has_push_flag && !has_dry_run_flag {
+        push_remote_url(&config)?;
+    }
+
+    let (remote_branch, commit_hash) = parse_if_maybe_hash(&config.remote_branch, " @ ");
+
+    let locked = if has_locked_flag {
+        Some(read_locked(&config, &remote_branch)?)
+    } else {
+        None
+    };
+
+    let commit_hash = match &locked {
+        Some(Lockfile {
+            base: Some(base), ..
+        }) => Some(base.commit.clone()),
+        _ => commit_hash,
+    };
+
+    let jobs = match jobs_flag {
+        Some(jobs) => jobs
+            .parse()
+            .map_err(|err| anyhow!("Invalid value for {}: {jobs}\n{err}", RUN_JOBS_FLAG.long))?,
+        None => config.jobs.unwrap_or(DEFAULT_JOBS),
+    };
+
+    if jobs == 0 {
+        return Err(anyhow!("At least 1 pull request must be fetched at a time"));
+    }
+
+    let repo = parse_repo(&config)?;
+
+    if !has_dry_run_flag {
+        save_new_resolutions();
+    }
+
+    let autostash = if has_dry_run_flag {
+        None
+    } else {
+        stash_changes(args)?
+    };
+
+    // A dry run does all of its work in a clone, which is deleted once it goes out of scope
+    let throwaway_clone = if has_dry_run_flag {
+        let clone = create_throwaway_clone()
+            .map_err(|err| anyhow!("Could not create a temporary clone for the dry run\n{err}"))?;
+        set_git_work_dir(Some(clone.path().into()));
+        info!("Dry run: your branches, remotes and files will not be changed");
+        Some(clone)
+    } else {
+        None
+    };
+
+    // A dry run downloads into the clone, so that the cache of the user's repository is left as is
+    let cache = match &throwaway_clone {
+        Some(clone) => clone.path().join(CONFIG_ROOT).join(PATCH_CACHE),
+        None => patch_cache(),
+    };
+    let downloaded_patches = download_patches(&config, &cache).await?;
+
+    if let Err(err) = load_resolutions() {
+        fail!("Could not load conflict resolutions from {CONFIG_ROOT}/{RERERE_CACHE}\n{err}");
+    }
+
+    // What happened to each pull request and patch, listed at the end of a dry run
+    let mut report = vec![];
+    let mut failed = Failed::new();
+
+    let mut lockfile = Lockfile::default();
 
     let info = BranchAndRemote {
         branch: Branch {
@@ -114,77 +1026,529 @@ pub async fn run(args: &CommandArgs) -> anyhow::Result<()> {
             local_branch_name: with_uuid(&remote_branch),
         },
         remote: Remote {
-            repository_url: format!("https://github.com/{}.git", config.repo),
-            local_remote_alias: with_uuid(&config.repo),
+            repository_url: repo.repository_url(config.transport),
+            local_remote_alias: with_uuid(&repo.path),
         },
     };
 
-    add_remote_branch(&info, &commit_hash)?;
+    add_remote_branch(&info, &commit_hash, &None)?;
 
-    let previous_branch = checkout_from_remote(
-        &info.branch.local_branch_name,
-        &info.remote.local_remote_alias,
-    )?;
+    // Everything from now on happens in a separate worktree, which is removed when it goes out of scope
+    let worktree = match TemporaryWorktree::enter(&info.branch.local_branch_name) {
+        Ok(worktree) => worktree,
+        Err(err) => {
+            clean_up_remote(
+                &info.remote.local_remote_alias,
+                &info.branch.local_branch_name,
+            )?;
+            return Err(err);
+        }
+    };
+
+    let clients = Clients::new(&repo, config.github_token.as_deref());
+
+    for pull_request in config.pull_requests.iter().filter(|entry| !entry.enabled) {
+        match &pull_request.note {
+            Some(note) => info!(
+                "Leaving out {} since it is disabled: {note}",
+                pull_request_label(&pull_request.number)
+            ),
+            None => info!(
+                "Leaving out {} since it is disabled",
+                pull_request_label(&pull_request.number)
+            ),
+        }
+    }
 
-    let client = reqwest::Client::new();
+    let enabled = config
+        .pull_requests
+        .iter()
+        .filter(|pull_request| pull_request.enabled)
+        .count();
 
-    if config.pull_requests.is_empty() {
+    let fetches = if config.pull_requests.is_empty() {
         info!(
             "You haven't specified any pull requests to fetch in your config, {}",
             display_link(
                 "see the instructions on how to configure patchy.",
                 "https://github.com/nik-rev/patchy?tab=readme-ov-file#config"
             )
+        );
+        vec![]
+    } else if enabled == 0 {
+        info!("All pull requests in your config are disabled");
+        vec![]
+    } else {
+        info!("Fetching {enabled} pull requests, up to {jobs} at a time");
+
+        spawn_pull_request_fetches(
+            &config,
+            &pull_requests,
+            &repo,
+            &clients,
+            jobs,
+            locked.as_ref(),
         )
+    };
+
+    lockfile.base = Some(LockedBase {
+        branch: remote_branch,
+        commit: GIT(&["rev-parse", "HEAD"])?,
+    });
+
+    // A dry run reports every pull request, so it never stops early
+    let on_failure = if has_interactive_flag {
+        OnFailure::Pause
+    } else if has_strict_flag && !has_dry_run_flag {
+        OnFailure::Stop
     } else {
-        // TODO: make this concurrent, see https://users.rust-lang.org/t/processing-subprocesses-concurrently/79638/3
-        // Git cannot handle multiple threads executing commands in the same repository, so we can't use threads, but we can run processes in the background
-        for pull_request in config.pull_requests.iter() {
-            let pull_request = ignore_octothorpe(pull_request);
-            let (pull_request, commit_hash) = parse_if_maybe_hash(&pull_request, " @ ");
-            // TODO: refactor this to not use such deep nesting
-            match fetch_pull_request(&config.repo, &pull_request, &client, None, &commit_hash).await
-            {
-                Ok((response, info)) => {
-                    match merge_pull_request(
-                        info,
-                        &pull_request,
-                        &response.title,
-                        &response.html_url,
-                    )
-                    .await
-                    {
-                        Ok(()) => {
-                            success!(
-                                "Merged pull request {}",
-                                display_link(
-                                    &format!(
-                                        "{}{}{}{}",
-                                        "#".bright_blue(),
-                                        pull_request.bright_blue(),
-                                        " ".bright_blue(),
-                                        &response.title.bright_blue().italic()
-                                    ),
-                                    &response.html_url
-                                ),
-                            )
-                        }
-                        Err(err) => {
-                            fail!("{err}");
-                            continue;
-                        }
-                    };
-                }
-                Err(err) => {
-                    fail!("Could not fetch branch from remote\n{err}");
-                    continue;
+        OnFailure::Skip
+    };
+
+    if let Some(paused) = merge_pull_requests(
+        &config,
+        &repo,
+        fetches,
+        on_failure,
+        &mut report,
+        &mut failed,
+        &mut lockfile,
+    )
+    .await?
+    {
+        print_run_report(
+            &config,
+            (&failed, &lockfile),
+            Some(&paused.0.pull_request),
+            vec![],
+            false,
+            false,
+            None,
+        );
+        pause_run(&repo, worktree, info, paused, report, failed, lockfile)?;
+        drop(autostash);
+        std::process::exit(1)
+    }
+
+    if on_failure == OnFailure::Stop && report.iter().any(Result::is_err) {
+        drop(worktree);
+        clean_up_remote(
+            &info.remote.local_remote_alias,
+            &info.branch.local_branch_name,
+        )?;
+        drop(autostash);
+
+        print_skipped(&config, &failed, &[]);
+        print_run_report(
+            &config,
+            (&failed, &lockfile),
+            None,
+            vec![],
+            false,
+            false,
+            None,
+        );
+
+        return Err(anyhow!(
+            "Stopped since {} was passed, branch {} was not changed",
+            RUN_STRICT_FLAG.long,
+            config.local_branch.cyan()
+        ));
+    }
+
+    if throwaway_clone.is_none() {
+        if has_prune_flag {
+            prune_closed(&config, &mut lockfile)?;
+        }
+        lockfile.write()?;
+    }
+
+    finish_run(
+        args,
+        (&config, &downloaded_patches),
+        &info,
+        worktree,
+        autostash,
+        (report, failed, lockfile),
+        throwaway_clone,
+    )
+}
+
+/// Resumes a run which was paused at a merge conflict by `--interactive`
+async fn resume_run(args: &CommandArgs, resume: Resume) -> anyhow::Result<()> {
+    let Some(state) = RunState::load()? else {
+        return Err(anyhow!("There is no paused run to resume"));
+    };
+
+    let RunState {
+        worktree,
+        base,
+        paused,
+        remaining,
+        mut report,
+        mut failed,
+        mut lockfile,
+    } = state;
+
+    if resume == Resume::Abort {
+        drop(TemporaryWorktree::resume(worktree));
+
+        let fetched = remaining
+            .iter()
+            .filter_map(|(_, fetched)| fetched.as_ref().ok().and_then(|(_, info)| info.as_ref()));
+
+        // Some of these may already be gone, which is fine since we are removing them anyways
+        for info in [&base, &paused.info].into_iter().chain(fetched) {
+            let _ = clean_up_remote(
+                &info.remote.local_remote_alias,
+                &info.branch.local_branch_name,
+            );
+        }
+
+        RunState::remove()?;
+        success!("Aborted the paused run, your branches were not changed");
+
+        return Ok(());
+    }
+
+    let config = read_config(args)?;
+    let repo = parse_repo(&config)?;
+    let downloaded_patches = download_patches(&config, &patch_cache()).await?;
+
+    // The uncommitted changes are those of the user's checkout, not of the worktree
+    let autostash = stash_changes(args)?;
+
+    if !worktree.exists() {
+        return Err(anyhow!(
+            "The worktree of the paused run at {worktree:?} no longer exists, use {} to discard the run",
+            RUN_ABORT_FLAG.long.bright_magenta()
+        ));
+    }
+
+    let worktree = TemporaryWorktree::resume(worktree);
+
+    let pr = format_pr(
+        &paused.pull_request,
+        &paused.response.title,
+        &paused.response.html_url,
+    );
+    let pull_request_name = repo.forge.pull_request_name();
+
+    match resume {
+        Resume::Continue => {
+            let conflicts = unmerged_files()?;
+
+            if !conflicts.is_empty() {
+                worktree.pause();
+                return Err(anyhow!(
+                    "There are still merge conflicts in:\n{}\nResolve them and stage them with {}, then run {} again",
+                    conflicts
+                        .iter()
+                        .map(|file| format!("{INDENT}{INDENT}{}", file.bright_yellow()))
+                        .collect::<Vec<_>>()
+                        .join("\n"),
+                    "git add".bright_blue(),
+                    format!("{APP_NAME} run {}", RUN_CONTINUE_FLAG.long).bright_magenta()
+                ));
+            }
+
+            let strategy = config.merge_strategy(&paused.pull_request);
+
+            if cherry_pick_in_progress()? {
+                // The rest of the commits are cherry-picked too, which can stop at another conflict
+                if let Err(err) = continue_cherry_pick() {
+                    let conflicts = unmerged_files()?;
+                    worktree.pause();
+                    return Err(anyhow!(
+                        "{err}\nThere are merge conflicts in:\n{}\nResolve them and stage them with {}, then run {} again",
+                        conflicts
+                            .iter()
+                            .map(|file| format!("{INDENT}{INDENT}{}", file.bright_yellow()))
+                            .collect::<Vec<_>>()
+                            .join("\n"),
+                        "git add".bright_blue(),
+                        format!("{APP_NAME} run {}", RUN_CONTINUE_FLAG.long).bright_magenta()
+                    ));
                 }
+            // The user might have committed the merge themselves
+            } else if GIT(&["diff", "--cached", "--quiet"]).is_err() {
+                GIT(&[
+                    "-c",
+                    "rerere.enabled=true",
+                    "commit",
+                    "--message",
+                    &format!("patchy: Merge {}", paused.info.branch.local_branch_name),
+                ])?;
             }
+
+            success!("Merged {pull_request_name} {pr} using {strategy}");
+            report.push(Ok(format!(
+                "{pr} merges using {strategy} after resolving conflicts"
+            )));
         }
+        Resume::Skip => {
+            if cherry_pick_in_progress()? {
+                // Also drops the commits of the pull request which were already cherry-picked
+                GIT(&["cherry-pick", "--abort"])?;
+            } else {
+                GIT(&["reset", "--hard"])?;
+            }
+
+            info!("Skipped {pull_request_name} {pr}");
+            report.push(Err(format!("{pr} was skipped")));
+            failed.insert(paused.pull_request.clone(), "was skipped".into());
+        }
+        Resume::Abort => unreachable!("aborting was handled above"),
     }
 
-    if let Err(err) = fs::create_dir_all(GIT_ROOT.join(CONFIG_ROOT)) {
-        GIT(&["checkout", &previous_branch])?;
+    clean_up_remote(
+        &paused.info.remote.local_remote_alias,
+        &paused.info.branch.local_branch_name,
+    )?;
+
+    RunState::remove()?;
+
+    save_new_resolutions();
+
+    let fetches = remaining
+        .into_iter()
+        .map(|(pull_request, fetched)| {
+            let fetched = fetched.map_err(|err| anyhow!(err));
+            (pull_request, tokio::spawn(std::future::ready(fetched)))
+        })
+        .collect();
+
+    if let Some(paused) = merge_pull_requests(
+        &config,
+        &repo,
+        fetches,
+        OnFailure::Pause,
+        &mut report,
+        &mut failed,
+        &mut lockfile,
+    )
+    .await?
+    {
+        print_run_report(
+            &config,
+            (&failed, &lockfile),
+            Some(&paused.0.pull_request),
+            vec![],
+            false,
+            false,
+            None,
+        );
+        pause_run(&repo, worktree, base, paused, report, failed, lockfile)?;
+        drop(autostash);
+        std::process::exit(1)
+    }
+
+    if RUN_PRUNE_FLAG.is_in_args(args) {
+        prune_closed(&config, &mut lockfile)?;
+    }
+    lockfile.write()?;
+
+    finish_run(
+        args,
+        (&config, &downloaded_patches),
+        &base,
+        worktree,
+        autostash,
+        (report, failed, lockfile),
+        None,
+    )
+}
+
+/// URL of the remote which `--push` pushes to
+fn push_remote_url(config: &Configuration) -> anyhow::Result<String> {
+    let (remote, _) = config.push_target();
+
+    GIT(&["remote", "get-url", remote]).map_err(|err| {
+        anyhow!(
+            "Could not find remote {remote} to push to. Add it with {}, or set `push-remote` in {CONFIG_ROOT}/{CONFIG_FILE}\n{err}",
+            format!("git remote add {remote} <url>").bright_magenta()
+        )
+    })
+}
+
+/// Pushes `local-branch` to `push-remote`, unless something was left out of it and
+/// `--push-anyway` wasn't passed
+///
+/// Returns the URL of the remote it was pushed to
+fn push_result(
+    config: &Configuration,
+    skipped: usize,
+    push_anyway: bool,
+) -> anyhow::Result<Option<String>> {
+    let (remote, remote_branch) = config.push_target();
+    let url = push_remote_url(config)?;
+
+    if skipped > 0 && !push_anyway {
+        fail!(
+            "Did not push to {url} since pull requests or patches were left out of the result. Use {} to push anyway",
+            RUN_PUSH_ANYWAY_FLAG.long.bright_magenta()
+        );
+        return Ok(None);
+    }
+
+    push_with_lease(remote, &config.local_branch, remote_branch).map_err(|err| {
+        anyhow!(
+            "Could not push branch {} to {url}, it was only updated locally. If the remote branch changed since you last fetched it, fetch it and check what changed before running again\n{err}",
+            config.local_branch.cyan()
+        )
+    })?;
+
+    success!(
+        "Pushed branch {} to branch {} of {url}",
+        config.local_branch.cyan(),
+        remote_branch.cyan()
+    );
+
+    Ok(Some(url))
+}
+
+/// Prints the report of a run with `--output=json`, based on what happened to each pull request
+///
+/// `paused` is the pull request a run paused at with `--interactive`
+fn print_run_report(
+    config: &Configuration,
+    (failed, lockfile): (&Failed, &Lockfile),
+    paused: Option<&str>,
+    patches: Vec<PatchReport>,
+    dry_run: bool,
+    updated: bool,
+    pushed: Option<String>,
+) {
+    if !*IS_JSON {
+        return;
+    }
+
+    let pull_requests = config
+        .ordered_pull_requests()
+        .unwrap_or_else(|_| config.pull_requests.iter().collect())
+        .into_iter()
+        .map(|entry| {
+            let locked = lockfile.pull_request(&entry.number);
+
+            let (outcome, error) = if !entry.enabled {
+                (Outcome::Disabled, None)
+            } else if paused == Some(entry.number.as_str()) {
+                (Outcome::Paused, None)
+            } else if let Some(why) = failed.get(&entry.number) {
+                (Outcome::Skipped, Some(why.clone()))
+            } else {
+                match locked {
+                    Some(locked) if locked.closed => (Outcome::AlreadyMerged, None),
+                    Some(_) => (Outcome::Merged, None),
+                    None => (Outcome::NotAttempted, None),
+                }
+            };
+
+            PullRequestReport {
+                number: entry.number.clone(),
+                title: locked.map(|locked| locked.title.clone()),
+                url: locked.map(|locked| locked.url.clone()),
+                commit: locked.map(|locked| locked.commit.clone()),
+                strategy: Some(config.merge_strategy(&entry.number)),
+                branch: None,
+                outcome,
+                error,
+            }
+        })
+        .collect();
+
+    print_json(&RunReport {
+        dry_run,
+        local_branch: config.local_branch.clone(),
+        updated,
+        pushed,
+        base: lockfile.base.clone(),
+        pull_requests,
+        patches,
+    });
+}
+
+/// Lists what was left out of the run, one per line in a format meant for scripts:
+/// `skipped pull-request <number>: <reason>` and `skipped patch <name>: <reason>`
+///
+/// Returns how many were left out. Disabled pull requests don't count, since leaving them out is intended
+fn print_skipped(
+    config: &Configuration,
+    failed: &Failed,
+    skipped_patches: &[(String, String)],
+) -> usize {
+    let skipped: Vec<_> = config
+        .pull_requests
+        .iter()
+        .filter(|entry| entry.enabled)
+        .filter_map(|entry| {
+            failed
+                .get(&entry.number)
+                .map(|why| format!("skipped pull-request {}: {why}", entry.number))
+        })
+        .chain(
+            skipped_patches
+                .iter()
+                .map(|(patch, why)| format!("skipped patch {patch}: {why}")),
+        )
+        .collect();
+
+    if !skipped.is_empty() {
+        human!(
+            "\n{INDENT}{}\n",
+            "Left out of the run:".bright_yellow().bold()
+        );
+        human!("{}\n", skipped.join("\n"));
+    }
+
+    skipped.len()
+}
+
+/// Why a patch didn't apply, from the first error git gave
+fn patch_failure_reason(err: &anyhow::Error) -> String {
+    err.to_string()
+        .lines()
+        .find_map(|line| {
+            let line = line.trim();
+            line.strip_prefix("Stderr: ")
+                .unwrap_or(line)
+                .strip_prefix("error: ")
+        })
+        .map_or_else(
+            || "does not apply".to_owned(),
+            |error| format!("does not apply ({error})"),
+        )
+}
+
+/// Applies the patches, and overwrites local-branch with the result once the pull requests are merged
+fn finish_run(
+    args: &CommandArgs,
+    (config, downloaded_patches): (&Configuration, &DownloadedPatches),
+    info: &BranchAndRemote,
+    worktree: TemporaryWorktree,
+    autostash: Option<Autostash>,
+    (mut report, failed, lockfile): (Report, Failed, Lockfile),
+    throwaway_clone: Option<TempDir>,
+) -> anyhow::Result<()> {
+    let config_path = GIT_ROOT.join(CONFIG_ROOT);
+    let has_yes_flag = RUN_YES_FLAG.is_in_args(args);
+    let has_strict_flag = RUN_STRICT_FLAG.is_in_args(args);
+    let has_push_anyway_flag = RUN_PUSH_ANYWAY_FLAG.is_in_args(args);
+    let has_push_flag = RUN_PUSH_FLAG.is_in_args(args) || has_push_anyway_flag;
+
+    let config_files = fs::read_dir(&config_path).map_err(|err| {
+        anyhow!(
+            "Could not read files in directory {:?}\n{err}",
+            &config_path
+        )
+    })?;
+
+    let backed_up_files = backup_files(config_files).map_err(|err| {
+        anyhow!("Could not create backups for configuration files, aborting.\n{err}")
+    })?;
+
+    if let Err(err) = fs::create_dir_all(git_work_dir().join(CONFIG_ROOT)) {
+        drop(worktree);
 
         clean_up_remote(
             &info.remote.local_remote_alias,
@@ -194,45 +1558,155 @@ pub async fn run(args: &CommandArgs) -> anyhow::Result<()> {
         return Err(anyhow!("Could not create directory {CONFIG_ROOT}\n{err}"));
     };
 
-    for (file_name, _file, contents) in backed_up_files.iter() {
-        restore_backup(file_name, contents)
+    // Patches which don't apply, and why
+    let mut skipped_patches = vec![];
+    let mut patch_reports = vec![];
+
+    for (backed_up_name, _file, contents) in backed_up_files.iter() {
+        restore_backup(backed_up_name, contents)
             .map_err(|err| anyhow!("Could not restore backups:\n{err}"))?;
+    }
 
-        // apply patches if they exist
-        if let Some(ref patches) = config.patches {
-            let file_name = file_name
-                .to_str()
-                .and_then(|file_name| file_name.get(0..file_name.len() - 6))
-                .unwrap_or_default();
-
-            if patches.contains(file_name) {
-                if let Err(err) = GIT(&[
-                    "am",
-                    "--keep-cr",
-                    "--signoff",
-                    &format!(
-                        "{}/{file_name}.patch",
-                        GIT_ROOT.join(CONFIG_ROOT).to_str().unwrap_or_default()
-                    ),
-                ]) {
-                    GIT(&["am", "--abort"])?;
-                    return Err(anyhow!(
-                        "Could not apply patch {file_name}, skipping\n{err}"
-                    ));
-                };
+    // Patches can build on top of each other, so they are applied in the order of the config
+    for patch in config.patches.iter().flatten() {
+        let (file_name, patch_path) = match &patch.source {
+            PatchSource::File(name) => (
+                name.as_str(),
+                git_work_dir()
+                    .join(CONFIG_ROOT)
+                    .join(format!("{name}.patch")),
+            ),
+            // Every patch with a URL was downloaded before the run started
+            PatchSource::Url { url, .. } => match downloaded_patches.get(url) {
+                Some(path) => (url.as_str(), path.clone()),
+                None => continue,
+            },
+        };
 
-                let last_commit_message = GIT(&["log", "-1", "--format=%B"])?;
-                success!(
-                    "Applied patch {file_name} {}",
-                    last_commit_message
-                        .lines()
-                        .next()
-                        .unwrap_or_default()
-                        .bright_blue()
-                        .italic()
+        let options = config.apply_options(patch);
+        // --strict promises that nothing is left out, which is stronger than what the patch asks for
+        let skip = options.on_failure == PatchFailure::Skip && !has_strict_flag;
+
+        let commits = match apply_patch(
+            &patch_path,
+            file_name,
+            patch.author.as_deref(),
+            patch.message.as_deref(),
+            options,
+        ) {
+            Ok(commits) => commits,
+            // A dry run reports every patch, so it keeps going
+            Err(err) if skip || throwaway_clone.is_some() => {
+                if skip {
+                    fail!("Could not apply patch {file_name}, skipping it\n{err}");
+                } else {
+                    fail!("Could not apply patch {file_name}\n{err}");
+                }
+                report.push(Err(format!("Patch {file_name} does not apply")));
+                skipped_patches.push((file_name.to_owned(), patch_failure_reason(&err)));
+                patch_reports.push(PatchReport {
+                    name: file_name.to_owned(),
+                    commit: None,
+                    commits: vec![],
+                    outcome: if skip {
+                        Outcome::Skipped
+                    } else {
+                        Outcome::Failed
+                    },
+                    error: Some(err.to_string()),
+                });
+                continue;
+            }
+            Err(err) => {
+                drop(worktree);
+                clean_up_remote(
+                    &info.remote.local_remote_alias,
+                    &info.branch.local_branch_name,
+                )?;
+
+                patch_reports.push(PatchReport {
+                    name: file_name.to_owned(),
+                    commit: None,
+                    commits: vec![],
+                    outcome: Outcome::Failed,
+                    error: Some(err.to_string()),
+                });
+                print_run_report(
+                    config,
+                    (&failed, &lockfile),
+                    None,
+                    patch_reports,
+                    false,
+                    false,
+                    None,
                 );
+
+                return Err(anyhow!(
+                    "Could not apply patch {file_name}, branch {} was not changed\n{err}",
+                    config.local_branch.cyan()
+                ));
             }
+        };
+
+        report.push(Ok(format!("Patch {file_name} applies")));
+
+        // An mbox can hold several commits, each of which is listed
+        for commit in &commits {
+            let subject = GIT(&["log", "-1", "--format=%s", commit])?;
+            success!(
+                "Applied patch {file_name} {}",
+                subject.bright_blue().italic()
+            );
         }
+
+        patch_reports.push(PatchReport {
+            name: file_name.to_owned(),
+            commit: commits.last().cloned(),
+            commits,
+            outcome: Outcome::Applied,
+            error: None,
+        });
+    }
+
+    // The patches were copied into the worktree, so the cache only has to keep the ones still in the config.
+    // A dry run leaves the cache alone, as it did not download into it
+    if throwaway_clone.is_none() {
+        if let Err(err) = remove_unused_patches(downloaded_patches) {
+            fail!("Could not remove unused patches from {CONFIG_ROOT}/{PATCH_CACHE}\n{err}");
+        }
+    }
+
+    if throwaway_clone.is_some() {
+        drop(worktree);
+        set_git_work_dir(None);
+
+        human!("\n{INDENT}{}\n", "Dry run report".bold());
+
+        for entry in report {
+            match entry {
+                Ok(message) => success!("{message}"),
+                Err(message) => fail!("{message}"),
+            }
+        }
+
+        let skipped = print_skipped(config, &failed, &skipped_patches);
+        print_run_report(
+            config,
+            (&failed, &lockfile),
+            None,
+            patch_reports,
+            true,
+            false,
+            None,
+        );
+
+        if skipped > 0 {
+            // Exiting skips destructors, which would leave the clone behind
+            drop(throwaway_clone);
+            std::process::exit(2);
+        }
+
+        return Ok(());
     }
 
     GIT(&["add", CONFIG_ROOT])?;
@@ -246,26 +1720,51 @@ pub async fn run(args: &CommandArgs) -> anyhow::Result<()> {
 
     GIT(&["switch", "--create", &temporary_branch])?;
 
+    // Removing the worktree frees up the temporary branch to be renamed
+    drop(worktree);
+
     clean_up_remote(
         &info.remote.local_remote_alias,
         &info.branch.local_branch_name,
     )?;
 
+    let is_on_local_branch = GIT(&["rev-parse", "--abbrev-ref", "HEAD"])
+        .is_ok_and(|branch| branch == config.local_branch);
+
     if has_yes_flag
         || confirm_prompt!(
             "Overwrite branch {}? This is irreversible.",
             config.local_branch.cyan()
         )
     {
-        // forcefully renames the branch we are currently on into the branch specified by the user.
-        // WARNING: this is a destructive action which erases the original branch
-        GIT(&[
-            "branch",
-            "--move",
-            "--force",
-            &temporary_branch,
-            &config.local_branch,
-        ])?;
+        if is_on_local_branch {
+            // git won't rename a branch onto the one that is checked out, so move the checkout along with it.
+            // Changes in the config directory are already part of the result, and the others are stashed
+            // with --autostash, but any made during the run would be lost
+            if !uncommitted_changes()?.is_empty() {
+                return Err(anyhow!(
+                    "You have uncommitted changes on branch {}, so it was not overwritten. \
+                    Commit or stash them, then move it to the result with: {}",
+                    config.local_branch.cyan(),
+                    format!("git reset --hard {temporary_branch}").bright_magenta()
+                ));
+            }
+
+            GIT(&["reset", "--hard", &temporary_branch])?;
+            GIT(&["branch", "--delete", "--force", &temporary_branch])?;
+        } else {
+            // forcefully renames the branch with the result into the branch specified by the user.
+            // WARNING: this is a destructive action which erases the original branch
+            GIT(&[
+                "branch",
+                "--move",
+                "--force",
+                &temporary_branch,
+                &config.local_branch,
+            ])?;
+        }
+        drop(autostash);
+
         if has_yes_flag {
             info!(
                 "Overwrote branch {} since you supplied the {} flag",
@@ -273,17 +1772,63 @@ pub async fn run(args: &CommandArgs) -> anyhow::Result<()> {
                 "--yes".bright_magenta()
             );
         }
-        println!("\n{INDENT}{}", "  Success!\n".bright_green().bold());
+        let skipped = print_skipped(config, &failed, &skipped_patches);
+
+        let pushed = if has_push_flag {
+            match push_result(config, skipped, has_push_anyway_flag) {
+                Ok(pushed) => pushed,
+                Err(err) => {
+                    print_run_report(
+                        config,
+                        (&failed, &lockfile),
+                        None,
+                        patch_reports,
+                        false,
+                        true,
+                        None,
+                    );
+                    return Err(err);
+                }
+            }
+        } else {
+            None
+        };
+
+        print_run_report(
+            config,
+            (&failed, &lockfile),
+            None,
+            patch_reports,
+            false,
+            true,
+            pushed,
+        );
+
+        if skipped > 0 {
+            std::process::exit(2);
+        }
+
+        human!("\n{INDENT}{}", "  Success!\n".bright_green().bold());
     } else {
         let command = format!(
             "  git branch --move --force {temporary_branch} {}",
             config.local_branch
         );
         let command = format!("\n{INDENT}{}\n", command.bright_magenta());
-        println!(
+        print_run_report(
+            config,
+            (&failed, &lockfile),
+            None,
+            patch_reports,
+            false,
+            false,
+            None,
+        );
+        human!(
             "\n{INDENT}  You can still manually overwrite {} with the following command:\n  {command}",
             config.local_branch.cyan(),
         );
+        drop(autostash);
         std::process::exit(1)
     }
 
@@ -0,0 +1,265 @@
+use colored::{ColoredString, Colorize};
+use futures::{stream, StreamExt};
+
+use crate::{
+    commands::{
+        help,
+        run::{get_pull_request_entry, parse_repo, read_config, DEFAULT_JOBS},
+    },
+    fail,
+    flags::{is_valid_flag, Flag},
+    forge::{CiStatus, Clients},
+    info,
+    lockfile::Lockfile,
+    types::{CommandArgs, GitHubResponse, PullRequestState, Source},
+    utils::{display_link, pull_request_label, short_hash},
+    INDENT,
+};
+
+use super::help::{HELP_FLAG, VERSION_FLAG};
+
+pub static STATUS_FLAGS: &[&Flag<'static>; 2] = &[&HELP_FLAG, &VERSION_FLAG];
+
+/// Titles longer than this are cut off, to keep the table readable
+const MAX_TITLE_LENGTH: usize = 40;
+
+/// A cell of the table, which knows its width without the escape codes for colors and links
+struct Cell {
+    text: String,
+    width: usize,
+}
+
+impl Cell {
+    fn new(text: ColoredString) -> Self {
+        let width = text.chars().count();
+        Self {
+            text: text.to_string(),
+            width,
+        }
+    }
+
+    fn link(text: ColoredString, url: &str) -> Self {
+        let width = text.chars().count();
+        Self {
+            text: display_link(&text.to_string(), url),
+            width,
+        }
+    }
+}
+
+fn state_cell(response: &GitHubResponse) -> Cell {
+    Cell::new(match response.state {
+        PullRequestState::Open => "open".bright_green(),
+        PullRequestState::Closed if response.merged => "merged".bright_magenta(),
+        PullRequestState::Closed => "closed".bright_red(),
+    })
+}
+
+/// Compares the latest commit of the pull request with the one it is pinned to, if any
+fn pinned_cell(head: &str, pinned: Option<&str>) -> Cell {
+    Cell::new(match pinned {
+        None => "-".bright_black(),
+        Some(pinned) if pinned.starts_with(head) || head.starts_with(pinned) => {
+            short_hash(pinned).bright_green()
+        }
+        Some(pinned) => format!("{} (outdated)", short_hash(pinned)).bright_yellow(),
+    })
+}
+
+/// Shows a timestamp such as `2024-11-05T14:03:27Z` as `2024-11-05 14:03`
+fn updated_cell(updated_at: &str) -> Cell {
+    Cell::new(
+        updated_at
+            .get(..16)
+            .unwrap_or(updated_at)
+            .replacen('T', " ", 1)
+            .normal(),
+    )
+}
+
+fn mergeable_cell(response: &GitHubResponse) -> Cell {
+    Cell::new(match response.mergeable {
+        _ if response.state == PullRequestState::Closed => "-".bright_black(),
+        Some(true) => "yes".bright_green(),
+        Some(false) => "conflicts".bright_red(),
+        None => "unknown".bright_black(),
+    })
+}
+
+fn ci_cell(ci: Option<CiStatus>) -> Cell {
+    Cell::new(match ci {
+        Some(CiStatus::Success) => "passing".bright_green(),
+        Some(CiStatus::Pending) => "pending".bright_yellow(),
+        Some(CiStatus::Failure) => "failing".bright_red(),
+        Some(CiStatus::Missing) => "none".bright_black(),
+        None => "unknown".bright_black(),
+    })
+}
+
+fn print_row(cells: &[Cell], widths: &[usize]) {
+    let row = cells
+        .iter()
+        .zip(widths)
+        .map(|(cell, width)| {
+            format!(
+                "{}{}",
+                cell.text,
+                " ".repeat(width.saturating_sub(cell.width))
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("  ");
+
+    println!("{INDENT}{}", row.trim_end());
+}
+
+/// Shows the state of every pull request in the config, without running
+pub async fn status(args: &CommandArgs) -> anyhow::Result<()> {
+    for arg in args {
+        if arg.starts_with('-') && !is_valid_flag(arg, STATUS_FLAGS) {
+            fail!("Invalid flag: {arg}");
+            let _ = help(Some("status"));
+            std::process::exit(1);
+        }
+    }
+
+    println!();
+
+    let config = read_config(args)?;
+    let repo = parse_repo(&config)?;
+    let lockfile = Lockfile::read()?;
+
+    if config.pull_requests.is_empty() {
+        info!("You haven't specified any pull requests in your config");
+        return Ok(());
+    }
+
+    let clients = Clients::new(&repo, config.github_token.as_deref());
+
+    let pull_requests: Vec<(String, Option<String>)> = config
+        .pull_requests
+        .iter()
+        .filter(|pull_request| pull_request.enabled)
+        .map(|pull_request| (pull_request.number.clone(), pull_request.commit.clone()))
+        .collect();
+
+    // As many pull requests are looked up at the same time as `run` fetches, in the same order as the config
+    let statuses: Vec<_> = stream::iter(
+        config
+            .pull_requests
+            .iter()
+            .filter(|pull_request| pull_request.enabled)
+            .map(|entry| async {
+                let response =
+                    get_pull_request_entry(&repo, &clients, &entry.number, &entry.source).await?;
+
+                // Branches without a pull request have no checks to look up
+                let ci_repo = match &entry.source {
+                    Source::PullRequest => Some(repo.clone()),
+                    Source::OtherRepo {
+                        repo: other_repo, ..
+                    } => repo.other_repo(other_repo).ok(),
+                    Source::Branch { .. } => None,
+                };
+
+                // The checks are less important than the rest, so don't let them hide the pull request
+                let ci = match ci_repo {
+                    Some(ci_repo) => ci_repo
+                        .get_ci_status(&clients.get(&ci_repo)?, &response.head.sha)
+                        .await
+                        .ok(),
+                    None => None,
+                };
+
+                anyhow::Ok((response, ci))
+            }),
+    )
+    .buffered(config.jobs.unwrap_or(DEFAULT_JOBS).max(1))
+    .collect()
+    .await;
+
+    let header = [
+        repo.forge.pull_request_name(),
+        "state",
+        "head",
+        "pinned",
+        "updated",
+        "mergeable",
+        "ci",
+    ]
+    .map(|title| Cell::new(title.to_uppercase().bold()));
+
+    let mut rows = vec![header];
+    let mut errors = vec![];
+
+    for ((pull_request, commit_hash), status) in pull_requests.iter().zip(statuses) {
+        let (response, ci) = match status {
+            Ok(status) => status,
+            Err(err) => {
+                errors.push(format!(
+                    "Could not fetch {}\n{err}",
+                    pull_request_label(pull_request)
+                ));
+                continue;
+            }
+        };
+
+        let title = if response.title.chars().count() > MAX_TITLE_LENGTH {
+            format!(
+                "{}…",
+                response
+                    .title
+                    .chars()
+                    .take(MAX_TITLE_LENGTH - 1)
+                    .collect::<String>()
+            )
+        } else {
+            response.title.clone()
+        };
+
+        // A commit pinned in the config wins over the one in the lockfile, just like in `run`
+        let pinned = commit_hash.as_deref().or_else(|| {
+            lockfile
+                .as_ref()
+                .and_then(|lockfile| lockfile.pull_request(pull_request))
+                .map(|locked| locked.commit.as_str())
+        });
+
+        rows.push([
+            Cell::link(
+                // Branches without a pull request are named by their title already
+                if pull_request.contains(':') {
+                    title.bright_blue()
+                } else {
+                    format!("{} {title}", pull_request_label(pull_request)).bright_blue()
+                },
+                &response.html_url,
+            ),
+            state_cell(&response),
+            Cell::new(short_hash(&response.head.sha).normal()),
+            pinned_cell(&response.head.sha, pinned),
+            updated_cell(&response.updated_at),
+            mergeable_cell(&response),
+            ci_cell(ci),
+        ]);
+    }
+
+    let mut widths = [0; 7];
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.width);
+        }
+    }
+
+    for row in &rows {
+        print_row(row, &widths);
+    }
+
+    println!();
+
+    for err in errors {
+        fail!("{err}");
+    }
+
+    Ok(())
+}
@@ -0,0 +1,169 @@
+use colored::Colorize;
+use futures::future::join_all;
+
+use crate::{
+    commands::{
+        help,
+        pr_fetch::ignore_octothorpe,
+        run::{get_pull_request_entry, parse_if_maybe_hash, parse_repo, read_config},
+    },
+    fail,
+    flags::{is_valid_flag, Flag},
+    forge::Clients,
+    git_commands::GIT,
+    info,
+    lockfile::{LockedBase, LockedPullRequest, Lockfile, LOCKFILE},
+    success,
+    types::{CommandArgs, PullRequestState, Source},
+    utils::{format_pr, pull_request_label, short_hash},
+    CONFIG_ROOT,
+};
+
+use super::help::{HELP_FLAG, VERSION_FLAG};
+
+pub static UPDATE_FLAGS: &[&Flag<'static>; 2] = &[&HELP_FLAG, &VERSION_FLAG];
+
+/// Refreshes the lockfile with the latest commits, of every pull request or only of the given ones
+pub async fn update(args: &CommandArgs) -> anyhow::Result<()> {
+    let mut requested = vec![];
+
+    let mut no_more_flags = false;
+
+    for arg in args {
+        // After "--", each argument is interpreted literally
+        if arg == "--" {
+            no_more_flags = true;
+            continue;
+        };
+
+        if arg.starts_with('-') && !no_more_flags {
+            if !is_valid_flag(arg, UPDATE_FLAGS) {
+                fail!("Invalid flag: {arg}");
+                let _ = help(Some("update"));
+                std::process::exit(1);
+            }
+
+            continue;
+        }
+
+        requested.push(ignore_octothorpe(arg));
+    }
+
+    let config = read_config(args)?;
+    let repo = parse_repo(&config)?;
+
+    let pull_requests: Vec<(String, Source, Option<String>)> = config
+        .pull_requests
+        .iter()
+        .filter(|pull_request| pull_request.enabled)
+        .map(|pull_request| {
+            (
+                pull_request.number.clone(),
+                pull_request.source.clone(),
+                pull_request.commit.clone(),
+            )
+        })
+        .collect();
+
+    if let Some(missing) = requested.iter().find(|requested| {
+        !pull_requests
+            .iter()
+            .any(|(number, _, _)| number == *requested)
+    }) {
+        return Err(anyhow::anyhow!(
+            "{} is not one of the enabled `pull-requests` in your config",
+            pull_request_label(missing)
+        ));
+    }
+
+    let update_all = requested.is_empty();
+
+    let mut lockfile = Lockfile::read()?.unwrap_or_default();
+
+    let targets: Vec<_> = pull_requests
+        .iter()
+        .filter(|(number, _, _)| update_all || requested.contains(number))
+        .collect();
+
+    let clients = Clients::new(&repo, config.github_token.as_deref());
+
+    let responses = join_all(
+        targets
+            .iter()
+            .map(|(number, source, _)| get_pull_request_entry(&repo, &clients, number, source)),
+    )
+    .await;
+
+    for ((number, _, commit_hash), response) in targets.into_iter().zip(responses) {
+        let response = response?;
+
+        // A commit pinned in the config takes precedence over the latest one
+        let commit = commit_hash.clone().unwrap_or(response.head.sha);
+        let pr = format_pr(number, &response.title, &response.html_url);
+
+        match lockfile.pull_request(number) {
+            Some(locked) if locked.commit == commit => {
+                info!(
+                    "{pr} is up to date at {}",
+                    short_hash(&commit).bright_cyan()
+                )
+            }
+            Some(locked) => success!(
+                "Updated {pr} from {} to {}",
+                short_hash(&locked.commit).bright_cyan(),
+                short_hash(&commit).bright_cyan()
+            ),
+            None => success!("Locked {pr} at {}", short_hash(&commit).bright_cyan()),
+        }
+
+        lockfile.lock_pull_request(LockedPullRequest {
+            number: number.clone(),
+            commit,
+            title: response.title,
+            url: response.html_url,
+            closed: response.state == PullRequestState::Closed,
+        });
+    }
+
+    if update_all {
+        let (branch, commit_hash) = parse_if_maybe_hash(&config.remote_branch, " @ ");
+
+        let commit = match commit_hash {
+            Some(commit) => commit,
+            None => {
+                let url = repo.repository_url(config.transport);
+
+                GIT(&["ls-remote", &url, &format!("refs/heads/{branch}")])?
+                    .split_whitespace()
+                    .next()
+                    .map(String::from)
+                    .ok_or_else(|| {
+                        anyhow::anyhow!("Could not find branch {branch} of repository {url}")
+                    })?
+            }
+        };
+
+        success!(
+            "Locked branch {} at {}",
+            branch.cyan(),
+            short_hash(&commit).bright_cyan()
+        );
+
+        lockfile.base = Some(LockedBase { branch, commit });
+
+        // Keep the same order as the config, and forget pull requests which were removed from it
+        lockfile.pull_requests = pull_requests
+            .iter()
+            .filter_map(|(number, _, _)| lockfile.pull_request(number).cloned())
+            .collect();
+    }
+
+    lockfile.write()?;
+
+    info!(
+        "Wrote {}",
+        format!("{CONFIG_ROOT}/{LOCKFILE}").bright_cyan()
+    );
+
+    Ok(())
+}
@@ -0,0 +1,388 @@
+use std::{fs, path::PathBuf};
+
+use anyhow::anyhow;
+use serde::Deserialize;
+use toml::de::ValueDeserializer;
+use toml_edit::{Array, DocumentMut, InlineTable, Item, Value};
+
+use crate::{
+    git_commands::GIT_ROOT,
+    types::{PullRequestEntry, Source},
+    CONFIG_FILE, CONFIG_ROOT,
+};
+
+/// The config file, parsed in a way that keeps its formatting and comments so it can be edited in place
+pub struct ConfigFile {
+    document: DocumentMut,
+}
+
+impl ConfigFile {
+    fn path() -> PathBuf {
+        GIT_ROOT.join(CONFIG_ROOT).join(CONFIG_FILE)
+    }
+
+    pub fn read() -> anyhow::Result<Self> {
+        let config = fs::read_to_string(Self::path())
+            .map_err(|err| anyhow!("Could not read {CONFIG_ROOT}/{CONFIG_FILE}\n{err}"))?;
+
+        let document = config.parse().map_err(|err| {
+            anyhow!("Could not parse `{CONFIG_ROOT}/{CONFIG_FILE}` configuration file:\n{err}")
+        })?;
+
+        Ok(Self { document })
+    }
+
+    pub fn write(&self) -> anyhow::Result<()> {
+        fs::write(Self::path(), self.document.to_string())
+            .map_err(|err| anyhow!("Could not write {CONFIG_ROOT}/{CONFIG_FILE}\n{err}"))
+    }
+
+    /// The list under `key`, if the config has it
+    fn list(&self, key: &str) -> anyhow::Result<Option<&Array>> {
+        self.document
+            .get(key)
+            .map(|list| {
+                list.as_array()
+                    .ok_or_else(|| anyhow!("`{key}` in {CONFIG_ROOT}/{CONFIG_FILE} is not a list"))
+            })
+            .transpose()
+    }
+
+    /// The list under `key`, which is created if the config doesn't have it yet
+    fn list_mut(&mut self, key: &str) -> anyhow::Result<&mut Array> {
+        if !self.document.contains_key(key) {
+            self.document
+                .insert(key, Item::Value(Value::Array(Array::new())));
+
+            // Separate it from the keys above it
+            if let Some(mut new_key) = self.document.key_mut(key) {
+                new_key.leaf_decor_mut().set_prefix("\n");
+            }
+        }
+
+        self.document
+            .get_mut(key)
+            .and_then(|list| list.as_array_mut())
+            .ok_or_else(|| anyhow!("`{key}` in {CONFIG_ROOT}/{CONFIG_FILE} is not a list"))
+    }
+
+    fn pull_request_position(
+        &self,
+        pull_request: &PullRequestEntry,
+    ) -> anyhow::Result<Option<usize>> {
+        Ok(self.list("pull-requests")?.and_then(|pull_requests| {
+            pull_requests.iter().position(|entry| {
+                pull_request_entry(entry).is_ok_and(|entry| entry.is_same(pull_request))
+            })
+        }))
+    }
+
+    pub fn has_pull_request(&self, pull_request: &PullRequestEntry) -> anyhow::Result<bool> {
+        Ok(self.pull_request_position(pull_request)?.is_some())
+    }
+
+    /// The commit a pull request is pinned to in `pull-requests`
+    pub fn pull_request_commit(
+        &self,
+        pull_request: &PullRequestEntry,
+    ) -> anyhow::Result<Option<String>> {
+        let Some(position) = self.pull_request_position(pull_request)? else {
+            return Ok(None);
+        };
+
+        Ok(self
+            .list("pull-requests")?
+            .and_then(|pull_requests| pull_requests.get(position))
+            .and_then(|pull_request| pull_request_entry(pull_request).ok())
+            .and_then(|entry| entry.commit))
+    }
+
+    /// Adds a pull request to the end of `pull-requests`, in its short form unless it is a branch
+    pub fn add_pull_request(&mut self, pull_request: &PullRequestEntry) -> anyhow::Result<()> {
+        let value = match &pull_request.source {
+            Source::Branch { remote, branch } => {
+                let mut table = InlineTable::new();
+                table.insert("remote", Value::from(remote.as_str()));
+                table.insert("branch", Value::from(branch.as_str()));
+                if let Some(commit) = &pull_request.commit {
+                    table.insert("commit", Value::from(commit.as_str()));
+                }
+                table.fmt();
+                Value::InlineTable(table)
+            }
+            Source::PullRequest | Source::OtherRepo { .. } => {
+                Value::from(match &pull_request.commit {
+                    Some(commit) => format!("{} @ {commit}", pull_request.number),
+                    None => pull_request.number.clone(),
+                })
+            }
+        };
+
+        push_entry(self.list_mut("pull-requests")?, value);
+        Ok(())
+    }
+
+    /// Pins a pull request in `pull-requests` to a commit, or unpins it, keeping the comments
+    /// around it and the rest of its table if it is written as one
+    ///
+    /// Returns whether it was in the list
+    pub fn set_pull_request_commit(
+        &mut self,
+        pull_request: &PullRequestEntry,
+        commit: Option<&str>,
+    ) -> anyhow::Result<bool> {
+        let number = &pull_request.number;
+        let Some(position) = self.pull_request_position(pull_request)? else {
+            return Ok(false);
+        };
+
+        let pull_requests = self.list_mut("pull-requests")?;
+
+        let Some(old) = pull_requests.get(position) else {
+            return Ok(false);
+        };
+
+        let mut value = match old.as_inline_table() {
+            Some(table) => {
+                let mut table = table.clone();
+                match commit {
+                    Some(commit) if table.contains_key("commit") => {
+                        table.insert("commit", Value::from(commit));
+                    }
+                    Some(commit) => {
+                        // The space before the closing brace belongs to the last value, so it moves
+                        // over to the new one
+                        let mut value = Value::from(commit);
+                        if let Some((_, last)) = table.iter_mut().last() {
+                            if let Some(suffix) = last.decor().suffix().cloned() {
+                                last.decor_mut().set_suffix("");
+                                value.decor_mut().set_suffix(suffix);
+                            }
+                        }
+                        value.decor_mut().set_prefix(" ");
+                        table.insert("commit", value);
+                    }
+                    None => {
+                        // Only the last value holds the space before the closing brace
+                        let is_last = table.iter().last().is_some_and(|(key, _)| key == "commit");
+                        let suffix = table
+                            .get("commit")
+                            .and_then(|commit| commit.decor().suffix().cloned())
+                            .filter(|_| is_last);
+                        table.remove("commit");
+                        if let (Some(suffix), Some((_, last))) = (suffix, table.iter_mut().last()) {
+                            last.decor_mut().set_suffix(suffix);
+                        }
+                    }
+                }
+                Value::InlineTable(table)
+            }
+            None => Value::from(match commit {
+                Some(commit) => format!("{number} @ {commit}"),
+                None => number.to_owned(),
+            }),
+        };
+
+        *value.decor_mut() = old.decor().clone();
+        pull_requests.replace_formatted(position, value);
+
+        Ok(true)
+    }
+
+    /// Removes a pull request from `pull-requests`, along with the comments above it
+    ///
+    /// Returns whether it was in the list
+    pub fn remove_pull_request(&mut self, pull_request: &PullRequestEntry) -> anyhow::Result<bool> {
+        let Some(position) = self.pull_request_position(pull_request)? else {
+            return Ok(false);
+        };
+
+        remove_entry(self.list_mut("pull-requests")?, position);
+
+        Ok(true)
+    }
+
+    pub fn has_patch(&self, patch: &str) -> anyhow::Result<bool> {
+        Ok(self
+            .list("patches")?
+            .is_some_and(|patches| patches.iter().any(|entry| patch_name(entry) == Some(patch))))
+    }
+
+    /// Adds a patch to the end of `patches`
+    pub fn add_patch(&mut self, patch: &str) -> anyhow::Result<()> {
+        push_entry(self.list_mut("patches")?, Value::from(patch));
+        Ok(())
+    }
+
+    /// Removes a patch from `patches`, along with the comments above it
+    ///
+    /// Returns whether it was in the list
+    pub fn remove_patch(&mut self, patch: &str) -> anyhow::Result<bool> {
+        let Some(position) = self.list("patches")?.and_then(|patches| {
+            patches
+                .iter()
+                .position(|entry| patch_name(entry) == Some(patch))
+        }) else {
+            return Ok(false);
+        };
+
+        remove_entry(self.list_mut("patches")?, position);
+
+        Ok(true)
+    }
+}
+
+/// A pull request written as e.g. `#123 @ <hash>` or `{ number = 123, commit = "<hash>" }`
+fn pull_request_entry(pull_request: &Value) -> anyhow::Result<PullRequestEntry> {
+    // Comments around the entry are part of its decor, leave them out
+    let mut pull_request = pull_request.clone();
+    pull_request.decor_mut().clear();
+
+    Ok(PullRequestEntry::deserialize(ValueDeserializer::new(
+        &pull_request.to_string(),
+    ))?)
+}
+
+/// A patch written as e.g. `"my-patch"` or `{ name = "my-patch", author = "…" }`
+fn patch_name(patch: &Value) -> Option<&str> {
+    match patch.as_inline_table() {
+        Some(table) => table.get("name").and_then(|name| name.as_str()),
+        None => patch.as_str(),
+    }
+}
+
+/// Appends to a list, putting the new entry on its own line if the entries before it are
+fn push_entry(list: &mut Array, mut value: Value) {
+    let indent = list
+        .iter()
+        .last()
+        .and_then(|last| last.decor().prefix())
+        .and_then(|prefix| prefix.as_str())
+        .and_then(|prefix| prefix.rsplit_once('\n'))
+        .map(|(_, indent)| indent.to_owned());
+
+    match indent {
+        Some(indent) => value.decor_mut().set_prefix(format!("\n{indent}")),
+        None if list.is_empty() => value.decor_mut().set_prefix(""),
+        None => value.decor_mut().set_prefix(" "),
+    }
+
+    list.push_formatted(value);
+}
+
+/// Removes an entry from a list, along with the comments above it
+fn remove_entry(list: &mut Array, position: usize) {
+    let removed = list.remove(position);
+
+    // Comments on the same line as an entry are stored in front of the next one, so the comment
+    // of the previous entry moves over, while the one of the removed entry goes away
+    let (previous_comment, _) = split_same_line_comment(
+        removed
+            .decor()
+            .prefix()
+            .and_then(|prefix| prefix.as_str())
+            .unwrap_or_default(),
+    );
+    let replace_comment = |whitespace: &str| {
+        format!(
+            "{previous_comment}{}",
+            split_same_line_comment(whitespace).1
+        )
+    };
+
+    match list.get_mut(position) {
+        Some(next) => {
+            let decor = next.decor_mut();
+            let prefix = replace_comment(
+                decor
+                    .prefix()
+                    .and_then(|prefix| prefix.as_str())
+                    .unwrap_or_default(),
+            );
+            decor.set_prefix(prefix);
+        }
+        None => {
+            let trailing = replace_comment(list.trailing().as_str().unwrap_or_default());
+            list.set_trailing(trailing);
+        }
+    }
+}
+
+/// Splits whitespace into a comment which is on the same line as what came before it, and the rest
+fn split_same_line_comment(whitespace: &str) -> (&str, &str) {
+    match whitespace.find('\n') {
+        Some(newline) if whitespace[..newline].trim_start().starts_with('#') => {
+            whitespace.split_at(newline)
+        }
+        _ => ("", whitespace),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config_file(config: &str) -> ConfigFile {
+        ConfigFile {
+            document: config.parse().unwrap(),
+        }
+    }
+
+    fn entry(pull_request: &str) -> PullRequestEntry {
+        PullRequestEntry::parse_argument(pull_request).unwrap()
+    }
+
+    #[test]
+    fn unpins_tables() {
+        let mut config = config_file(
+            r#"pull-requests = [
+  { number = 12, commit = "abc" },
+  { number = 13, commit = "def", note = "keep" },
+]
+"#,
+        );
+
+        assert!(config.set_pull_request_commit(&entry("12"), None).unwrap());
+        assert!(config.set_pull_request_commit(&entry("13"), None).unwrap());
+
+        assert_eq!(
+            config.document.to_string(),
+            r#"pull-requests = [
+  { number = 12 },
+  { number = 13, note = "keep" },
+]
+"#
+        );
+    }
+
+    #[test]
+    fn pins_tables() {
+        let mut config = config_file("pull-requests = [{ number = 12 }]\n");
+
+        assert!(config
+            .set_pull_request_commit(&entry("12"), Some("abc"))
+            .unwrap());
+
+        assert_eq!(
+            config.document.to_string(),
+            "pull-requests = [{ number = 12, commit = \"abc\" }]\n"
+        );
+    }
+
+    #[test]
+    fn looks_up_without_adding_lists() {
+        let config = "repo = \"helix-editor/helix\"\n";
+        let mut config_file = config_file(config);
+
+        assert!(!config_file.has_pull_request(&entry("12")).unwrap());
+        assert_eq!(config_file.pull_request_commit(&entry("12")).unwrap(), None);
+        assert!(!config_file.has_patch("my-patch").unwrap());
+        assert!(!config_file.remove_patch("my-patch").unwrap());
+        assert!(!config_file.remove_pull_request(&entry("12")).unwrap());
+        assert!(!config_file
+            .set_pull_request_commit(&entry("12"), None)
+            .unwrap());
+
+        assert_eq!(config_file.document.to_string(), config);
+    }
+}
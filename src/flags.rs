@@ -3,9 +3,13 @@ use std::{env, fmt::Display};
 use colored::Colorize;
 use once_cell::sync::Lazy;
 
-use crate::{commands::help::format_description, types::CommandArgs};
+use crate::{
+    commands::help::{format_description, OUTPUT_FLAG},
+    types::CommandArgs,
+};
 
 pub struct Flag<'a> {
+    /// Empty for a flag which can only be written in full, such as one that can't be undone
     pub short: &'a str,
     pub long: &'a str,
     pub description: &'a str,
@@ -33,12 +37,19 @@ pub struct Flag<'a> {
 /// assert_eq!(invalid, None);
 /// ```
 impl Flag<'_> {
+    /// The ways to write this flag
+    fn forms(&self) -> impl Iterator<Item = &str> {
+        [self.short, self.long]
+            .into_iter()
+            .filter(|form| !form.is_empty())
+    }
+
     pub fn is_in_args(&self, args: &CommandArgs) -> bool {
-        args.contains(self.short) || args.contains(self.long)
+        self.forms().any(|form| args.contains(form))
     }
 
     pub fn extract_from_arg(&self, arg: &str) -> Option<String> {
-        if arg.starts_with(self.short) {
+        if !self.short.is_empty() && arg.starts_with(self.short) {
             arg.get(self.short.len()..).map(|value| value.into())
         } else if arg.starts_with(self.long) {
             arg.get(self.long.len()..).map(|value| value.into())
@@ -51,6 +62,15 @@ impl Flag<'_> {
 impl Display for Flag<'_> {
     /// Formats a flag into a colored format with a description, printable to the terminal
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.short.is_empty() {
+            return write!(
+                f,
+                "{}\n    {}",
+                self.long.bright_magenta(),
+                format_description(self.description)
+            );
+        }
+
         write!(
             f,
             "{}{}{}\n    {}",
@@ -67,7 +87,7 @@ pub fn is_valid_flag(arg: &str, available_flags: &[&Flag]) -> bool {
     // TODO: flags that don't end in "=" should be compared fully, not just the beginning
     available_flags
         .iter()
-        .flat_map(|flag| [flag.short, flag.long])
+        .flat_map(|flag| flag.forms())
         .any(|flag| arg.starts_with(flag))
 }
 
@@ -76,3 +96,31 @@ pub static IS_VERBOSE: Lazy<bool> = Lazy::new(|| {
     let args: CommandArgs = env::args().collect();
     args.contains("--verbose")
 });
+
+/// Makes commands print a JSON report for scripts, with `--output=json`
+pub static IS_JSON: Lazy<bool> = Lazy::new(|| {
+    env::args().any(|arg| OUTPUT_FLAG.extract_from_arg(&arg).as_deref() == Some("json"))
+});
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static LONG_ONLY_FLAG: Flag<'static> = Flag {
+        short: "",
+        long: "--abort",
+        description: "some flag",
+    };
+
+    #[test]
+    fn matches_long_only_flags_in_full() {
+        let args = |args: &[&str]| -> CommandArgs { args.iter().map(|&arg| arg.into()).collect() };
+
+        assert!(LONG_ONLY_FLAG.is_in_args(&args(&["--abort"])));
+        assert!(!LONG_ONLY_FLAG.is_in_args(&args(&["-a"])));
+        assert!(!LONG_ONLY_FLAG.is_in_args(&args(&[""])));
+        assert_eq!(LONG_ONLY_FLAG.extract_from_arg("-a"), None);
+        assert!(is_valid_flag("--abort", &[&LONG_ONLY_FLAG]));
+        assert!(!is_valid_flag("-a", &[&LONG_ONLY_FLAG]));
+    }
+}
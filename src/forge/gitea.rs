@@ -0,0 +1,158 @@
+use reqwest::Client;
+use serde::Deserialize;
+
+use crate::{types::GitHubResponse, utils::make_request};
+
+use super::{CiStatus, Forge, ForgeRepo};
+
+/// Gitea and its fork Forgejo respond with the same shape as GitHub, so no conversion is needed
+pub async fn get_pull_request(
+    client: &Client,
+    repo: &ForgeRepo,
+    pull_request: &str,
+) -> anyhow::Result<GitHubResponse> {
+    let url = format!(
+        "{}/api/v1/repos/{}/pulls/{pull_request}",
+        repo.base_url, repo.path
+    );
+
+    make_request(client, Forge::Gitea, &url).await
+}
+
+#[derive(Deserialize, Debug)]
+struct CombinedStatus {
+    state: String,
+    total_count: u64,
+}
+
+/// Combined status of the checks of a commit
+pub async fn get_ci_status(
+    client: &Client,
+    repo: &ForgeRepo,
+    commit: &str,
+) -> anyhow::Result<CiStatus> {
+    let url = format!(
+        "{}/api/v1/repos/{}/commits/{commit}/status",
+        repo.base_url, repo.path
+    );
+
+    let combined: CombinedStatus = make_request(client, Forge::Gitea, &url).await?;
+
+    Ok(if combined.total_count == 0 {
+        CiStatus::Missing
+    } else {
+        CiStatus::from_state(&combined.state)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        forge::{tests::serve, CiStatus, Forge},
+        types::PullRequestState,
+        utils::make_client,
+    };
+
+    const PULL_REQUEST: &str = r#"{
+        "title": "Add b",
+        "html_url": "http://gitea.test/owner/repo/pulls/1",
+        "state": "open",
+        "merged": false,
+        "mergeable": true,
+        "updated_at": "2026-10-17T20:00:00Z",
+        "head": {
+            "ref": "feature",
+            "sha": "3f2a1c9b1d9e3e34a1c3e0a4f0b6c1d2e3f4a5b6",
+            "repo": {
+                "clone_url": "http://gitea.test/fork/repo.git",
+                "ssh_url": "git@gitea.test:fork/repo.git"
+            }
+        }
+    }"#;
+
+    #[tokio::test]
+    async fn gets_pull_request() {
+        let repo = serve(
+            &[("/api/v1/repos/owner/repo/pulls/1", PULL_REQUEST)],
+            Forge::Gitea,
+        )
+        .await;
+        let client = make_client(Forge::Gitea, None).unwrap();
+
+        let pull_request = super::get_pull_request(&client, &repo, "1").await.unwrap();
+
+        assert_eq!(pull_request.title, "Add b");
+        assert_eq!(pull_request.state, PullRequestState::Open);
+        assert_eq!(pull_request.head.r#ref, "feature");
+        assert_eq!(
+            pull_request.head.sha,
+            "3f2a1c9b1d9e3e34a1c3e0a4f0b6c1d2e3f4a5b6"
+        );
+        assert_eq!(
+            pull_request.head.repo.clone_url,
+            "http://gitea.test/fork/repo.git"
+        );
+    }
+
+    #[tokio::test]
+    async fn missing_pull_request_is_an_error() {
+        let repo = serve(&[], Forge::Gitea).await;
+        let client = make_client(Forge::Gitea, None).unwrap();
+
+        let err = super::get_pull_request(&client, &repo, "404")
+            .await
+            .unwrap_err()
+            .to_string();
+
+        assert!(err.contains("404 Not Found"), "{err}");
+        assert!(err.contains("/api/v1/repos/owner/repo/pulls/404"), "{err}");
+    }
+
+    #[tokio::test]
+    async fn gets_ci_status() {
+        let repo = serve(
+            &[
+                (
+                    "/api/v1/repos/owner/repo/commits/passing/status",
+                    r#"{ "state": "success", "total_count": 2 }"#,
+                ),
+                (
+                    "/api/v1/repos/owner/repo/commits/failing/status",
+                    r#"{ "state": "failure", "total_count": 1 }"#,
+                ),
+                (
+                    "/api/v1/repos/owner/repo/commits/running/status",
+                    r#"{ "state": "pending", "total_count": 1 }"#,
+                ),
+                // Gitea reports a commit without any statuses as pending
+                (
+                    "/api/v1/repos/owner/repo/commits/unchecked/status",
+                    r#"{ "state": "pending", "total_count": 0 }"#,
+                ),
+            ],
+            Forge::Gitea,
+        )
+        .await;
+        let client = make_client(Forge::Gitea, None).unwrap();
+
+        for (commit, expected) in [
+            ("passing", CiStatus::Success),
+            ("failing", CiStatus::Failure),
+            ("running", CiStatus::Pending),
+            ("unchecked", CiStatus::Missing),
+        ] {
+            let status = super::get_ci_status(&client, &repo, commit).await.unwrap();
+            assert_eq!(status, expected, "{commit}");
+        }
+    }
+
+    #[tokio::test]
+    async fn ci_status_of_missing_commit_is_an_error() {
+        let repo = serve(&[], Forge::Gitea).await;
+        let client = make_client(Forge::Gitea, None).unwrap();
+
+        assert!(super::get_ci_status(&client, &repo, "missing")
+            .await
+            .is_err());
+    }
+}
@@ -0,0 +1,76 @@
+use reqwest::Client;
+use serde::Deserialize;
+
+use crate::{types::GitHubResponse, utils::make_request};
+
+use super::{CiStatus, Forge, ForgeRepo};
+
+/// Base URL of the REST API, which differs between github.com and GitHub Enterprise Server
+fn api_url(repo: &ForgeRepo) -> String {
+    if repo.host() == "github.com" {
+        "https://api.github.com".into()
+    } else {
+        format!("{}/api/v3", repo.base_url)
+    }
+}
+
+pub async fn get_pull_request(
+    client: &Client,
+    repo: &ForgeRepo,
+    pull_request: &str,
+) -> anyhow::Result<GitHubResponse> {
+    let url = format!("{}/repos/{}/pulls/{pull_request}", api_url(repo), repo.path);
+
+    make_request(client, Forge::GitHub, &url).await
+}
+
+/// Combined status of the legacy commit statuses of a commit
+#[derive(Deserialize, Debug)]
+struct CombinedStatus {
+    state: String,
+    total_count: u64,
+}
+
+#[derive(Deserialize, Debug)]
+struct CheckRuns {
+    check_runs: Vec<CheckRun>,
+}
+
+/// A check such as a GitHub Actions job
+#[derive(Deserialize, Debug)]
+struct CheckRun {
+    /// One of `queued`, `in_progress` or `completed`
+    status: String,
+    conclusion: Option<String>,
+}
+
+/// Status of the checks of a commit, which may come from both commit statuses and check runs
+pub async fn get_ci_status(
+    client: &Client,
+    repo: &ForgeRepo,
+    commit: &str,
+) -> anyhow::Result<CiStatus> {
+    let commit_url = format!("{}/repos/{}/commits/{commit}", api_url(repo), repo.path);
+
+    let combined: CombinedStatus =
+        make_request(client, Forge::GitHub, &format!("{commit_url}/status")).await?;
+    let check_runs: CheckRuns =
+        make_request(client, Forge::GitHub, &format!("{commit_url}/check-runs")).await?;
+
+    // GitHub reports "pending" for commits which don't have any statuses
+    let status = if combined.total_count == 0 {
+        CiStatus::Missing
+    } else {
+        CiStatus::from_state(&combined.state)
+    };
+
+    Ok(check_runs
+        .check_runs
+        .iter()
+        .map(|check_run| match check_run.conclusion.as_deref() {
+            _ if check_run.status != "completed" => CiStatus::Pending,
+            Some("success" | "neutral" | "skipped") => CiStatus::Success,
+            _ => CiStatus::Failure,
+        })
+        .fold(status, CiStatus::combine))
+}
@@ -0,0 +1,185 @@
+use reqwest::Client;
+use serde::Deserialize;
+
+use crate::{
+    types::{GitHubResponse, Head, PullRequestState, Repo},
+    utils::make_request,
+};
+
+use super::{CiStatus, Forge, ForgeRepo};
+
+#[derive(Deserialize, Debug)]
+pub struct GitLabMergeRequest {
+    pub title: String,
+    pub web_url: String,
+    pub source_branch: String,
+    pub source_project_id: u64,
+    pub sha: String,
+    /// One of `opened`, `closed`, `locked` or `merged`
+    pub state: String,
+    /// One of `can_be_merged`, `cannot_be_merged` or a state in which it is still being checked
+    pub merge_status: String,
+    pub updated_at: String,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct GitLabPipeline {
+    pub status: String,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct GitLabCommit {
+    pub last_pipeline: Option<GitLabPipeline>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct GitLabProject {
+    pub http_url_to_repo: String,
+    pub ssh_url_to_repo: String,
+}
+
+/// Project paths contain slashes, which need to be escaped to be used as an ID in the API
+fn project_id(path: &str) -> String {
+    path.replace('/', "%2F")
+}
+
+async fn get_raw_merge_request(
+    client: &Client,
+    repo: &ForgeRepo,
+    merge_request: &str,
+) -> anyhow::Result<GitLabMergeRequest> {
+    make_request(
+        client,
+        Forge::GitLab,
+        &format!(
+            "{}/api/v4/projects/{}/merge_requests/{merge_request}",
+            repo.base_url,
+            project_id(&repo.path)
+        ),
+    )
+    .await
+}
+
+/// Looks up a merge request and the project it comes from, which may be a fork
+pub async fn get_merge_request(
+    client: &Client,
+    repo: &ForgeRepo,
+    merge_request: &str,
+) -> anyhow::Result<GitHubResponse> {
+    let api_url = format!("{}/api/v4", repo.base_url);
+
+    let merge_request = get_raw_merge_request(client, repo, merge_request).await?;
+
+    let source_project: GitLabProject = make_request(
+        client,
+        Forge::GitLab,
+        &format!("{api_url}/projects/{}", merge_request.source_project_id),
+    )
+    .await?;
+
+    Ok(GitHubResponse {
+        head: Head {
+            repo: Repo {
+                clone_url: source_project.http_url_to_repo,
+                ssh_url: source_project.ssh_url_to_repo,
+            },
+            r#ref: merge_request.source_branch,
+            sha: merge_request.sha,
+        },
+        title: merge_request.title,
+        html_url: merge_request.web_url,
+        state: match merge_request.state.as_str() {
+            "opened" => PullRequestState::Open,
+            _ => PullRequestState::Closed,
+        },
+        merged: merge_request.state == "merged",
+        mergeable: match merge_request.merge_status.as_str() {
+            "can_be_merged" => Some(true),
+            "cannot_be_merged" => Some(false),
+            _ => None,
+        },
+        updated_at: merge_request.updated_at,
+    })
+}
+
+/// Status of the latest pipeline of a commit, such as the head of a merge request
+pub async fn get_ci_status(
+    client: &Client,
+    repo: &ForgeRepo,
+    commit: &str,
+) -> anyhow::Result<CiStatus> {
+    let commit: GitLabCommit = make_request(
+        client,
+        Forge::GitLab,
+        &format!(
+            "{}/api/v4/projects/{}/repository/commits/{commit}",
+            repo.base_url,
+            project_id(&repo.path)
+        ),
+    )
+    .await?;
+
+    Ok(match commit.last_pipeline {
+        None => CiStatus::Missing,
+        Some(pipeline) => match pipeline.status.as_str() {
+            "success" => CiStatus::Success,
+            "failed" | "canceled" => CiStatus::Failure,
+            _ => CiStatus::Pending,
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        forge::{tests::serve, CiStatus, Forge},
+        utils::make_client,
+    };
+
+    #[tokio::test]
+    async fn gets_ci_status_of_commit() {
+        let repo = serve(
+            &[
+                (
+                    "/api/v4/projects/owner%2Frepo/repository/commits/passing",
+                    r#"{ "id": "passing", "last_pipeline": { "status": "success" } }"#,
+                ),
+                (
+                    "/api/v4/projects/owner%2Frepo/repository/commits/failing",
+                    r#"{ "id": "failing", "last_pipeline": { "status": "failed" } }"#,
+                ),
+                (
+                    "/api/v4/projects/owner%2Frepo/repository/commits/running",
+                    r#"{ "id": "running", "last_pipeline": { "status": "running" } }"#,
+                ),
+                (
+                    "/api/v4/projects/owner%2Frepo/repository/commits/unchecked",
+                    r#"{ "id": "unchecked", "last_pipeline": null }"#,
+                ),
+            ],
+            Forge::GitLab,
+        )
+        .await;
+        let client = make_client(Forge::GitLab, None).unwrap();
+
+        for (commit, expected) in [
+            ("passing", CiStatus::Success),
+            ("failing", CiStatus::Failure),
+            ("running", CiStatus::Pending),
+            ("unchecked", CiStatus::Missing),
+        ] {
+            let status = super::get_ci_status(&client, &repo, commit).await.unwrap();
+            assert_eq!(status, expected, "{commit}");
+        }
+    }
+
+    #[tokio::test]
+    async fn ci_status_of_missing_commit_is_an_error() {
+        let repo = serve(&[], Forge::GitLab).await;
+        let client = make_client(Forge::GitLab, None).unwrap();
+
+        assert!(super::get_ci_status(&client, &repo, "missing")
+            .await
+            .is_err());
+    }
+}
@@ -1,18 +1,29 @@
-use crate::{fail, utils::display_link};
+use crate::{
+    fail, info,
+    rerere::RERERE_CACHE,
+    utils::{display_link, format_pr},
+};
 use colored::Colorize;
 use std::{
     path::{Path, PathBuf},
     process::Output,
+    sync::{Mutex, PoisonError, RwLock},
 };
 
 use anyhow::{anyhow, Result};
 use once_cell::sync::Lazy;
 use reqwest::Client;
+use tempfile::TempDir;
 
 use crate::{
+    forge::ForgeRepo,
     trace,
-    types::{Branch, BranchAndRemote, GitHubResponse, Remote},
-    utils::{make_request, normalize_commit_msg, with_uuid},
+    types::{
+        ApplyOptions, Branch, BranchAndRemote, GitHubResponse, Head, MergeStrategy,
+        PatchWhitespace, PullRequestState, Remote, Repo, Transport,
+    },
+    utils::{normalize_commit_msg, pull_request_label, short_hash, with_uuid},
+    APP_NAME, CONFIG_ROOT,
 };
 
 pub fn is_valid_branch_name(branch_name: &str) -> bool {
@@ -21,9 +32,6 @@ pub fn is_valid_branch_name(branch_name: &str) -> bool {
         .all(|ch| ch.is_alphanumeric() || ch == '.' || ch == '-' || ch == '/' || ch == '_')
 }
 
-pub static GITHUB_REMOTE_PREFIX: &str = "git@github.com:";
-pub static GITHUB_REMOTE_SUFFIX: &str = ".git";
-
 pub fn spawn_git(args: &[&str], git_dir: &Path) -> Result<Output, std::io::Error> {
     std::process::Command::new("git")
         .args(args)
@@ -64,28 +72,289 @@ pub static GIT_ROOT: Lazy<PathBuf> = Lazy::new(|| match get_git_root() {
     }
 });
 
+/// Repository in which `GIT` runs its commands instead of `GIT_ROOT`, if any
+static GIT_WORK_DIR: RwLock<Option<PathBuf>> = RwLock::new(None);
+
+/// Makes all following `GIT` commands run in another repository, or in `GIT_ROOT` again if `None`
+pub fn set_git_work_dir(dir: Option<PathBuf>) {
+    *GIT_WORK_DIR.write().unwrap_or_else(PoisonError::into_inner) = dir;
+}
+
+/// Repository in which `GIT` currently runs its commands
+pub fn git_work_dir() -> PathBuf {
+    GIT_WORK_DIR
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+        .clone()
+        .unwrap_or_else(|| GIT_ROOT.clone())
+}
+
+/// Clones the repository into a temporary directory, sharing its objects so that nothing needs to be copied
+///
+/// Nothing done inside of the clone can change the branches, remotes or files of the original repository
+pub fn create_throwaway_clone() -> anyhow::Result<TempDir> {
+    let dir = tempfile::Builder::new().prefix("patchy-").tempdir()?;
+
+    let root = GIT_ROOT.to_string_lossy();
+    let clone = dir.path().to_string_lossy();
+
+    GIT(&[
+        "clone",
+        "--quiet",
+        "--shared",
+        "--no-checkout",
+        &root,
+        &clone,
+    ])?;
+
+    // Merging creates commits, which requires an identity. Local config isn't cloned so copy it over
+    for (key, default) in [("user.name", APP_NAME), ("user.email", "patchy@localhost")] {
+        let value = GIT(&["config", key]).unwrap_or_else(|_| default.into());
+        let args = ["config", key, &value];
+        get_git_output(spawn_git(&args, dir.path())?, &args)?;
+    }
+
+    Ok(dir)
+}
+
+/// Lists staged, unstaged and untracked changes in the form of `git status --short`
+///
+/// Changes to the config directory are left out, since patchy reads those directly from the working tree
+pub fn uncommitted_changes() -> anyhow::Result<Vec<String>> {
+    let status = GIT(&["status", "--porcelain", "--untracked-files=all"])?;
+
+    Ok(status
+        .lines()
+        .filter(|line| {
+            !line
+                .get(3..)
+                .is_some_and(|path| path.starts_with(&format!("{CONFIG_ROOT}/")))
+        })
+        .map(String::from)
+        .collect())
+}
+
+/// Uncommitted changes which are stashed away while this exists, and restored when it is dropped
+pub struct Autostash {
+    message: String,
+}
+
+impl Autostash {
+    pub fn push() -> anyhow::Result<Self> {
+        let message = with_uuid(&format!("{APP_NAME}-autostash"));
+
+        GIT(&[
+            "stash",
+            "push",
+            "--include-untracked",
+            "--message",
+            &message,
+            "--",
+            ".",
+            &format!(":(exclude){CONFIG_ROOT}"),
+        ])
+        .map_err(|err| anyhow!("Could not stash uncommitted changes\n{err}"))?;
+
+        Ok(Self { message })
+    }
+
+    /// The stash this made, such as `stash@{1}`, which is not the latest one if another was made
+    /// after it
+    fn find(&self) -> anyhow::Result<String> {
+        GIT(&["stash", "list", "--format=%gd %gs"])?
+            .lines()
+            .find_map(|line| {
+                let (stash, subject) = line.split_once(' ')?;
+                subject.ends_with(&self.message).then(|| stash.to_owned())
+            })
+            .ok_or_else(|| anyhow!("There is no stash named {}", self.message))
+    }
+}
+
+impl Drop for Autostash {
+    fn drop(&mut self) {
+        if let Err(err) = self
+            .find()
+            .and_then(|stash| GIT(&["stash", "pop", "--index", &stash]))
+        {
+            fail!(
+                "Could not restore your uncommitted changes, they are kept in the stash as {}\n{err}",
+                self.message.bright_cyan()
+            );
+        } else {
+            trace!("Restored stashed changes {}", self.message);
+        }
+    }
+}
+
+/// A worktree in a temporary directory, in which all `GIT` commands run while it exists
+///
+/// It is removed when dropped, so the user's own working tree, index and current branch are never touched
+pub struct TemporaryWorktree {
+    path: PathBuf,
+    /// Repository the worktree belongs to, where `GIT` commands run again once it is removed
+    repository: PathBuf,
+    /// Whether the worktree is left in place when dropped, for a paused run
+    keep: bool,
+}
+
+impl TemporaryWorktree {
+    /// Checks out `branch` in a new worktree of the repository `GIT` currently runs in
+    pub fn enter(branch: &str) -> anyhow::Result<Self> {
+        let path = tempfile::Builder::new().prefix("patchy-").tempdir()?.keep();
+
+        if let Err(err) = GIT(&["worktree", "add", &path.to_string_lossy(), branch]) {
+            let _ = std::fs::remove_dir_all(&path);
+            return Err(anyhow!(
+                "Could not create a temporary worktree for branch {branch}\n{err}"
+            ));
+        }
+
+        let repository = git_work_dir();
+        set_git_work_dir(Some(path.clone()));
+
+        trace!("Working in temporary worktree {path:?}");
+
+        Ok(Self {
+            path,
+            repository,
+            keep: false,
+        })
+    }
+
+    /// Goes back into a worktree which was kept by `pause`
+    ///
+    /// The worktree doesn't need to exist anymore, in which case it is only forgotten about when dropped
+    pub fn resume(path: PathBuf) -> Self {
+        let repository = git_work_dir();
+
+        if path.exists() {
+            set_git_work_dir(Some(path.clone()));
+            trace!("Working in temporary worktree {path:?}");
+        }
+
+        Self {
+            path,
+            repository,
+            keep: false,
+        }
+    }
+
+    /// Leaves the worktree in place, so that a paused run can be resumed in it later
+    ///
+    /// Returns the path of the worktree
+    pub fn pause(mut self) -> PathBuf {
+        self.keep = true;
+        self.path.clone()
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for TemporaryWorktree {
+    fn drop(&mut self) {
+        set_git_work_dir(Some(self.repository.clone()));
+
+        if self.keep {
+            return;
+        }
+
+        if GIT(&[
+            "worktree",
+            "remove",
+            "--force",
+            &self.path.to_string_lossy(),
+        ])
+        .is_err()
+        {
+            let _ = std::fs::remove_dir_all(&self.path);
+            let _ = GIT(&["worktree", "prune"]);
+        }
+    }
+}
+
+/// Files with merge conflicts which haven't been resolved yet
+pub fn unmerged_files() -> anyhow::Result<Vec<String>> {
+    Ok(GIT(&["diff", "--name-only", "--diff-filter=U"])?
+        .lines()
+        .map(String::from)
+        .collect())
+}
+
 type Git = Lazy<Box<dyn Fn(&[&str]) -> Result<String> + Send + Sync>>;
 
 pub static GIT: Git = Lazy::new(|| {
     Box::new(move |args: &[&str]| -> Result<String> {
         trace!("$ git {}", args.join(" "));
-        get_git_output(spawn_git(args, &GIT_ROOT)?, args)
+        get_git_output(spawn_git(args, &git_work_dir())?, args)
     })
 });
 
+/// Adding and removing remotes edits `.git/config`, which git refuses to do while another process
+/// holds its lock. Pull requests are fetched concurrently, so these edits must take turns
+static REMOTE_CONFIG_LOCK: Mutex<()> = Mutex::new(());
+
+/// Repository and ref of the pull request in it, from which a pinned commit can be fetched when
+/// the branch of the pull request no longer has it
+pub type PullRequestRef = Option<(String, String)>;
+
+/// Makes sure that `commit` is in the repository, fetching it by its hash from `repository_url`
+/// or else from the ref of its pull request
+///
+/// The commit is no longer on its branch once that is force-pushed, but forges can still serve it
+fn fetch_commit(
+    repository_url: &str,
+    commit: &str,
+    pull_request_ref: &PullRequestRef,
+) -> anyhow::Result<()> {
+    let has_commit = || GIT(&["cat-file", "-e", &format!("{commit}^{{commit}}")]).is_ok();
+
+    if has_commit() {
+        return Ok(());
+    }
+
+    trace!("Commit {commit} is not on its branch, fetching it from {repository_url}");
+
+    if GIT(&["fetch", repository_url, commit]).is_ok() && has_commit() {
+        return Ok(());
+    }
+
+    if let Some((url, pull_request_ref)) = pull_request_ref {
+        trace!("Fetching {pull_request_ref} from {url} for commit {commit}");
+
+        if GIT(&["fetch", url, pull_request_ref]).is_ok() && has_commit() {
+            return Ok(());
+        }
+    }
+
+    Err(anyhow!(
+        "Commit {commit} is no longer available from {repository_url}, \
+        it was probably removed by a force-push"
+    ))
+}
+
 /// Fetches a branch of a remote into local. Optionally accepts a commit hash for versioning.
 pub fn add_remote_branch(
     info: &BranchAndRemote,
     commit_hash: &Option<String>,
+    pull_request_ref: &PullRequestRef,
 ) -> anyhow::Result<()> {
-    if let Err(err) = GIT(&[
-        "remote",
-        "add",
-        &info.remote.local_remote_alias,
-        &info.remote.repository_url,
-    ]) {
-        GIT(&["remote", "remove", &info.remote.local_remote_alias])?;
-        return Err(anyhow!("Could not fetch remote: {err}"));
+    {
+        let _lock = REMOTE_CONFIG_LOCK
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+
+        if let Err(err) = GIT(&[
+            "remote",
+            "add",
+            &info.remote.local_remote_alias,
+            &info.remote.repository_url,
+        ]) {
+            GIT(&["remote", "remove", &info.remote.local_remote_alias])?;
+            return Err(anyhow!("Could not fetch remote: {err}"));
+        }
     }
 
     trace!(
@@ -103,7 +372,7 @@ pub fn add_remote_branch(
         ),
     ]) {
         return Err(anyhow!(
-            "We couldn't find branch {} of GitHub repository {}. Are you sure it \
+            "We couldn't find branch {} of repository {}. Are you sure it \
                      exists?\n{err}",
             info.branch.upstream_branch_name,
             info.remote.repository_url
@@ -118,6 +387,8 @@ pub fn add_remote_branch(
     );
 
     if let Some(commit_hash) = commit_hash {
+        fetch_commit(&info.remote.repository_url, commit_hash, pull_request_ref)?;
+
         GIT(&[
             "branch",
             "--force",
@@ -142,73 +413,316 @@ pub fn add_remote_branch(
 /// Removes a remote and its branch
 pub fn clean_up_remote(remote: &str, branch: &str) -> anyhow::Result<()> {
     // NOTE: Caller needs to ensure this function only runs if the script created the branch or if the user gave explicit permission
+    let _lock = REMOTE_CONFIG_LOCK
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner);
     GIT(&["branch", "--delete", "--force", branch])?;
     GIT(&["remote", "remove", remote])?;
     Ok(())
 }
 
-pub fn checkout_from_remote(branch: &str, remote: &str) -> anyhow::Result<String> {
-    let current_branch = GIT(&["rev-parse", "--abbrev-ref", "HEAD"]).or_else(|err| {
-        clean_up_remote(remote, branch)?;
-        Err(anyhow!(
-            "Couldn't get the current branch. This usually happens \
-            when the current branch does not have any commits.\n{err}"
-        ))
-    })?;
+/// Git arguments which make it re-apply conflict resolutions recorded in earlier runs, and stage them
+const RERERE: [&str; 4] = ["-c", "rerere.enabled=true", "-c", "rerere.autoUpdate=true"];
+
+/// Merges `local_branch` into the current branch with the given `strategy`
+///
+/// If there are conflicts the merge is undone, unless `keep_conflicts` is set in which case
+/// they are left in the working tree to be resolved by the user
+pub fn merge_into_main(
+    local_branch: &str,
+    remote_branch: &str,
+    strategy: MergeStrategy,
+    keep_conflicts: bool,
+) -> anyhow::Result<String, anyhow::Error> {
+    trace!("Merging branch {local_branch} with strategy {strategy}");
+
+    if strategy == MergeStrategy::Rebase {
+        return match cherry_pick(local_branch) {
+            Ok(()) => Ok(format!("Merged {remote_branch} successfully")),
+            Err(err) if keep_conflicts && !unmerged_files()?.is_empty() => {
+                Err(anyhow!("Could not merge {remote_branch}\n{err}"))
+            }
+            Err(err) => {
+                GIT(&["cherry-pick", "--abort"])?;
+                Err(anyhow!("Could not merge {remote_branch}\n{err}"))
+            }
+        };
+    }
+
+    let message = format!("patchy: Merge {local_branch}");
+
+    let merge = match strategy {
+        MergeStrategy::Squash => GIT(&[&RERERE[..], &["merge", "--squash", local_branch]].concat()),
+        _ => GIT(&[
+            &RERERE[..],
+            &["merge", "--no-ff", "--message", &message, local_branch],
+        ]
+        .concat()),
+    };
+
+    let resolved = match merge {
+        Ok(_) => false,
+        Err(err) => {
+            let is_unmerged = !GIT(&["ls-files", "--unmerged"])?.is_empty();
+            let is_staged = GIT(&["diff", "--cached", "--quiet"]).is_err();
+
+            if is_unmerged && keep_conflicts {
+                return Err(anyhow!("Could not merge {remote_branch}\n{err}"));
+            }
+
+            if is_unmerged || !is_staged {
+                // nukes the worktree
+                GIT(&["reset", "--hard"])?;
+                return Err(anyhow!("Could not merge {remote_branch}\n{err}"));
+            }
+
+            info!("Resolved conflicts in {remote_branch} using a recorded resolution");
+            true
+        }
+    };
+
+    match strategy {
+        // --squash will NOT commit anything. So we need to make it manually
+        MergeStrategy::Squash => {
+            GIT(&["commit", "--message", &message])?;
+        }
+        // The merge commit is left for us to make when the merge stopped at conflicts
+        _ if resolved => {
+            GIT(&["commit", "--no-edit"])?;
+        }
+        _ => (),
+    }
+
+    Ok(format!("Merged {remote_branch} successfully"))
+}
+
+/// Cherry-picks each commit of `local_branch` which isn't in the current branch, skipping merge commits
+fn cherry_pick(local_branch: &str) -> anyhow::Result<()> {
+    let commits = GIT(&[
+        "rev-list",
+        "--reverse",
+        "--no-merges",
+        &format!("HEAD..{local_branch}"),
+    ])?;
+
+    if commits.is_empty() {
+        return Ok(());
+    }
 
-    if let Err(err) = GIT(&["checkout", branch]) {
-        clean_up_remote(remote, branch)?;
+    let mut args = [&RERERE[..], &["cherry-pick", "--allow-empty"]].concat();
+    args.extend(commits.lines());
+
+    match GIT(&args) {
+        Ok(_) => Ok(()),
+        Err(_) => continue_cherry_pick(),
+    }
+}
+
+/// Whether a cherry-pick stopped at one of its commits
+pub fn cherry_pick_in_progress() -> anyhow::Result<bool> {
+    let sequencer = git_work_dir().join(GIT(&["rev-parse", "--git-path", "sequencer"])?);
+
+    Ok(
+        sequencer.exists()
+            || GIT(&["rev-parse", "--quiet", "--verify", "CHERRY_PICK_HEAD"]).is_ok(),
+    )
+}
+
+/// Carries on with a cherry-pick which stopped, as long as each commit it stops at had its
+/// conflicts resolved by a recorded resolution, or became empty
+pub fn continue_cherry_pick() -> anyhow::Result<()> {
+    let mut previous = None;
+
+    while cherry_pick_in_progress()? {
+        let commit = GIT(&["rev-parse", "--quiet", "--verify", "CHERRY_PICK_HEAD"]).ok();
+        let short_commit = commit.as_deref().map(short_hash).unwrap_or("HEAD");
+
+        if !unmerged_files()?.is_empty() {
+            return Err(anyhow!("Could not cherry-pick commit {short_commit}"));
+        }
+
+        if previous.as_ref() == Some(&commit) {
+            return Err(anyhow!(
+                "Could not continue cherry-picking after commit {short_commit}"
+            ));
+        }
+
+        let is_staged = GIT(&["diff", "--cached", "--quiet"]).is_err();
+
+        // The result is checked on the next iteration, by whether the cherry-pick moved on
+        let _ = if commit.is_none() || is_staged {
+            GIT(&[
+                &RERERE[..],
+                &["-c", "core.editor=true", "cherry-pick", "--continue"],
+            ]
+            .concat())
+        } else {
+            trace!("Skipping commit {short_commit}, which is already in the branch");
+            GIT(&[&RERERE[..], &["cherry-pick", "--skip"]].concat())
+        };
+
+        previous = Some(commit);
+    }
+
+    Ok(())
+}
+
+/// Merges several branches into the current branch with a single merge commit
+///
+/// Octopus merges can't have conflicts, so the merge is undone if there are any
+pub fn merge_octopus(local_branches: &[&str]) -> anyhow::Result<()> {
+    let message = format!("patchy: Merge {}", local_branches.join(", "));
+
+    let mut args = vec![
+        "merge",
+        "--no-ff",
+        "--strategy",
+        "octopus",
+        "--message",
+        &message,
+    ];
+    args.extend(local_branches);
+
+    if let Err(err) = GIT(&args) {
+        GIT(&["reset", "--hard"])?;
         return Err(anyhow!(
-            "Could not checkout branch: {branch}, which belongs to remote {remote}\n{err}"
+            "Could not merge {}\n{err}",
+            local_branches.join(", ")
         ));
-    };
+    }
 
-    Ok(current_branch)
+    Ok(())
 }
 
-pub fn merge_into_main(
+/// Pushes `local_branch` to `remote_branch` of `remote`, overwriting it only if it is still at the
+/// commit it was at when it was last fetched, like `git push --force-with-lease`
+pub fn push_with_lease(
+    remote: &str,
     local_branch: &str,
     remote_branch: &str,
-) -> anyhow::Result<String, anyhow::Error> {
-    trace!("Merging branch {local_branch}");
+) -> anyhow::Result<()> {
+    GIT(&[
+        "push",
+        "--force-with-lease",
+        remote,
+        &format!("refs/heads/{local_branch}:refs/heads/{remote_branch}"),
+    ])?;
 
-    if let Err(err) = GIT(&["merge", "--squash", local_branch]) {
-        // nukes the worktree
-        GIT(&["reset", "--hard"])?;
-        return Err(anyhow!("Could not merge {remote_branch}\n{err}"));
+    Ok(())
+}
+
+/// How the changes of a patch file are written
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PatchFormat {
+    /// One or more emails as made by `git format-patch`, each with the author and message of a commit
+    Mbox,
+    /// Output of `git diff` or `diff -u`, which only has the changes
+    Diff,
+}
+
+pub fn patch_format(patch: &str) -> PatchFormat {
+    // An mbox starts with a `From <commit> <date>` line, while a single email may start right at
+    // its headers
+    let first_line = patch
+        .lines()
+        .find(|line| !line.trim().is_empty())
+        .unwrap_or_default();
+
+    if ["From ", "From:", "Subject:", "Date:"]
+        .iter()
+        .any(|start| first_line.starts_with(start))
+    {
+        PatchFormat::Mbox
+    } else {
+        PatchFormat::Diff
+    }
+}
+
+/// Applies a patch file on top of `HEAD`, leaving it where it was if the patch doesn't apply
+///
+/// An mbox is applied with `git am`, making a commit for each email in it. A plain diff has no
+/// commit of its own, so it is applied with `git apply --3way` and committed with the given author
+/// and message, or with the committer as the author and `patchy: Apply patch <name>`.
+///
+/// Returns the commits made, oldest first
+pub fn apply_patch(
+    patch_path: &Path,
+    name: &str,
+    author: Option<&str>,
+    message: Option<&str>,
+    options: ApplyOptions,
+) -> anyhow::Result<Vec<String>> {
+    let patch = std::fs::read_to_string(patch_path)
+        .map_err(|err| anyhow!("Could not read {}\n{err}", patch_path.display()))?;
+    let patch_path = patch_path.to_str().unwrap_or_default();
+
+    let previous_commit = GIT(&["rev-parse", "HEAD"])?;
+
+    let whitespace = match options.whitespace {
+        Some(PatchWhitespace::Fix) => Some("--whitespace=fix"),
+        Some(PatchWhitespace::Ignore) => Some("--ignore-whitespace"),
+        None => None,
     };
 
-    // --squash will NOT commit anything. So we need to make it manually
-    GIT(&[
-        "commit",
-        "--message",
-        &format!("patchy: Merge {local_branch}",),
-    ])?;
+    match patch_format(&patch) {
+        PatchFormat::Mbox => {
+            let mut am = vec!["am", "--keep-cr", "--signoff"];
+            if options.three_way {
+                am.push("--3way");
+            }
+            am.extend(whitespace);
+            am.push(patch_path);
 
-    Ok(format!("Merged {remote_branch} successfully"))
+            if let Err(err) = GIT(&am) {
+                // There is nothing to abort if the patch couldn't even be parsed
+                let _ = GIT(&["am", "--abort"]);
+                return Err(err);
+            }
+        }
+        PatchFormat::Diff => {
+            let message = message.map_or_else(|| format!("patchy: Apply patch {name}"), Into::into);
+
+            let mut commit = vec!["commit", "--signoff", "--message", &message];
+            if let Some(author) = author {
+                commit.extend(["--author", author]);
+            }
+
+            let mut apply = vec!["apply", "--3way"];
+            apply.extend(whitespace);
+            apply.push(patch_path);
+
+            if let Err(err) = GIT(&apply).and_then(|_| GIT(&commit)) {
+                // Leave out the part of the diff which did apply, and any conflicts
+                let _ = GIT(&["reset", "--hard", &previous_commit]);
+                return Err(err);
+            }
+        }
+    }
+
+    Ok(
+        GIT(&["rev-list", "--reverse", &format!("{previous_commit}..HEAD")])?
+            .lines()
+            .map(Into::into)
+            .collect(),
+    )
 }
 
 pub async fn merge_pull_request(
-    info: BranchAndRemote,
+    info: &BranchAndRemote,
     pull_request: &str,
     pr_title: &str,
     pr_url: &str,
+    strategy: MergeStrategy,
+    keep_conflicts: bool,
 ) -> anyhow::Result<()> {
     merge_into_main(
         &info.branch.local_branch_name,
         &info.branch.upstream_branch_name,
+        strategy,
+        keep_conflicts,
     )
     .map_err(|err| {
-        let pr = display_link(
-            &format!(
-                "{}{}{}{}",
-                "#".bright_blue(),
-                pull_request.bright_blue(),
-                " ".bright_blue(),
-                pr_title.bright_blue().italic()
-            ),
-            pr_url,
-        );
+        let pr = format_pr(pull_request, pr_title, pr_url);
 
         let support_url = display_link(
             "Merge conflicts (github)",
@@ -216,14 +730,24 @@ pub async fn merge_pull_request(
         )
         .bright_blue();
 
+        let branch = &info.branch.local_branch_name;
+        let command = match strategy {
+            MergeStrategy::Squash => format!("git merge --squash {branch}"),
+            MergeStrategy::Merge | MergeStrategy::Octopus => format!("git merge --no-ff {branch}"),
+            MergeStrategy::Rebase => format!("git cherry-pick HEAD..{branch}"),
+        };
+
         anyhow!(
             "Could not merge branch {} into the current branch for pull request {pr} \
-            since the merge is non-trivial.\nYou will need to merge it yourself:\n  {} \
-            {0}\nNote: To learn how to merge only once and re-use for subsequent \
+            since the merge is non-trivial.\nYou will need to merge it yourself:\n  {}\n\
+            Once you commit the merge, git records how you resolved the conflicts. \
+            The next run saves the resolution to {}, commit it to have it applied automatically \
+            from then on.\nNote: To learn how to merge only once and re-use for subsequent \
             invocations of patchy, see {support_url}\nSkipping this PR. Error \
              message from git:\n{err}",
-            &info.branch.local_branch_name.bright_cyan(),
-            "git merge --squash".bright_blue()
+            branch.bright_cyan(),
+            command.bright_blue(),
+            format!("{CONFIG_ROOT}/{RERERE_CACHE}").bright_cyan()
         )
     })?;
 
@@ -290,23 +814,100 @@ fn first_available_branch(branch: &str) -> AvailableBranch {
 }
 
 pub async fn fetch_pull_request(
-    repo: &str,
+    repo: &ForgeRepo,
     pull_request: &str,
     client: &Client,
     custom_branch_name: Option<&str>,
     commit_hash: &Option<String>,
+    transport: Transport,
 ) -> anyhow::Result<(GitHubResponse, BranchAndRemote)> {
-    let url = format!("https://api.github.com/repos/{}/pulls/{pull_request}", repo);
-
-    let response = make_request(client, &url)
+    let response = repo
+        .get_pull_request(client, pull_request)
         .await
-        .map_err(|err| anyhow!("Could not fetch pull request #{pull_request}\n{err}\n"))?;
+        .map_err(|err| {
+            anyhow!(
+                "Could not fetch {} #{pull_request}\n{err}\n",
+                repo.forge.pull_request_name()
+            )
+        })?;
+
+    let info = fetch_pull_request_branch(
+        &response,
+        pull_request,
+        custom_branch_name,
+        commit_hash,
+        transport,
+        Some((
+            repo.repository_url(transport),
+            repo.pull_request_ref(pull_request),
+        )),
+    )
+    .await?;
+
+    Ok((response, info))
+}
+
+/// Looks up the latest commit of a branch which has no pull request, in the same shape as a pull
+/// request so that it can be fetched and merged like one
+pub fn get_branch(remote: &str, branch: &str) -> anyhow::Result<GitHubResponse> {
+    let refs = GIT(&["ls-remote", "--heads", remote, branch])
+        .map_err(|err| anyhow!("Could not reach {remote}\n{err}"))?;
+
+    let full_ref = format!("refs/heads/{branch}");
+    let sha = refs
+        .lines()
+        .filter_map(|line| line.split_once('\t'))
+        .find(|(_, name)| *name == full_ref)
+        .map(|(sha, _)| sha.to_owned())
+        .ok_or_else(|| anyhow!("Could not find branch {branch} of {remote}"))?;
+
+    Ok(GitHubResponse {
+        head: Head {
+            repo: Repo {
+                clone_url: remote.into(),
+                ssh_url: remote.into(),
+            },
+            r#ref: branch.into(),
+            sha,
+        },
+        title: format!("branch {branch} of {remote}"),
+        html_url: remote.into(),
+        state: PullRequestState::Open,
+        merged: false,
+        mergeable: None,
+        updated_at: String::new(),
+    })
+}
 
+/// Name of the local branch a pull request is fetched into, which starts with the pull request
+/// so that branches of the same name from different pull requests or remotes don't collide
+fn local_branch_name(pull_request: &str, head_ref: &str) -> String {
+    // A branch without a pull request is referred to as `<remote>:<branch>`, which isn't a valid
+    // branch name
+    match pull_request.rsplit_once(':') {
+        // A leading `-` would make git read the branch as an option
+        Some((remote, _)) => format!(
+            "{}/{head_ref}",
+            normalize_commit_msg(remote).trim_matches('-')
+        ),
+        None => format!("{pull_request}/{head_ref}"),
+    }
+}
+
+/// Fetches the branch of a pull request which was already looked up, as a local branch
+pub async fn fetch_pull_request_branch(
+    response: &GitHubResponse,
+    pull_request: &str,
+    custom_branch_name: Option<&str>,
+    commit_hash: &Option<String>,
+    transport: Transport,
+    pull_request_ref: PullRequestRef,
+) -> anyhow::Result<BranchAndRemote> {
     let info = BranchAndRemote {
         branch: Branch {
             upstream_branch_name: response.head.r#ref.clone(),
             local_branch_name: custom_branch_name.map(|s| s.into()).unwrap_or({
-                let branch_name = &format!("{pull_request}/{}", &response.head.r#ref);
+                let branch_name = &local_branch_name(pull_request, &response.head.r#ref);
 
                 match first_available_branch(branch_name) {
                     AvailableBranch::First => branch_name.to_string(),
@@ -315,18 +916,125 @@ pub async fn fetch_pull_request(
             }),
         },
         remote: Remote {
-            repository_url: response.head.repo.clone_url.clone(),
+            repository_url: transport.clone_url(&response.head.repo).into(),
             local_remote_alias: with_uuid(&format!(
                 "{title}-{}",
-                pull_request,
+                normalize_commit_msg(pull_request),
                 title = normalize_commit_msg(&response.html_url)
             )),
         },
     };
 
-    add_remote_branch(&info, commit_hash).map_err(|err| {
-        anyhow!("Could not add remote branch for pull request #{pull_request}, skipping.\n{err}")
+    // `git fetch` is blocking and can take a while, so keep it off the async runtime's threads
+    // to let other pull requests be fetched at the same time
+    let commit_hash = commit_hash.clone();
+    let info = tokio::task::spawn_blocking(move || {
+        add_remote_branch(&info, &commit_hash, &pull_request_ref).map(|()| info)
+    })
+    .await?
+    .map_err(|err| {
+        anyhow!(
+            "Could not add remote branch for {}, skipping.\n{err}",
+            pull_request_label(pull_request)
+        )
     })?;
 
-    Ok((response, info))
+    Ok(info)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn restores_its_own_stash() {
+        let repository = TempDir::new().unwrap();
+        set_git_work_dir(Some(repository.path().into()));
+        let write = |contents: &str| std::fs::write(repository.path().join("file"), contents);
+
+        GIT(&["init"]).unwrap();
+        GIT(&["config", "user.name", "patchy"]).unwrap();
+        GIT(&["config", "user.email", "patchy@example.com"]).unwrap();
+        write("committed").unwrap();
+        GIT(&["add", "file"]).unwrap();
+        GIT(&["commit", "--message", "add file"]).unwrap();
+
+        write("stashed by patchy").unwrap();
+        let autostash = Autostash::push().unwrap();
+        write("stashed by someone else").unwrap();
+        GIT(&["stash", "push", "--message", "someone else"]).unwrap();
+
+        drop(autostash);
+
+        let contents = std::fs::read_to_string(repository.path().join("file")).unwrap();
+        let stashes = GIT(&["stash", "list", "--format=%gs"]).unwrap();
+        set_git_work_dir(None);
+
+        assert_eq!(contents, "stashed by patchy");
+        assert!(stashes.ends_with("someone else"), "{stashes}");
+    }
+
+    #[test]
+    fn names_local_branches_after_their_source() {
+        assert_eq!(local_branch_name("12", "fix"), "12/fix");
+        assert_eq!(
+            local_branch_name("helix-editor/helix#12", "fix"),
+            "helix-editor/helix#12/fix"
+        );
+        assert_eq!(local_branch_name("a:fix", "fix"), "a/fix");
+        assert_eq!(
+            local_branch_name("/srv/fork.git:fix", "fix"),
+            "srv-fork-git/fix"
+        );
+        assert_ne!(
+            local_branch_name("https://github.com/a/helix:fix", "fix"),
+            local_branch_name("https://github.com/b/helix:fix", "fix")
+        );
+    }
+
+    #[test]
+    fn detects_mbox() {
+        let format_patch = "From 90c0db7342910092698936e1633fefd5432b02e0 Mon Sep 17 00:00:00 2001
+From: a <a@b>
+Date: Sat, 17 Oct 2026 20:58:29 +0000
+Subject: [PATCH] add d
+
+---
+ d.txt | 1 +
+";
+        assert_eq!(patch_format(format_patch), PatchFormat::Mbox);
+
+        // A single email without the `From <commit>` line, possibly after a blank line
+        assert_eq!(
+            patch_format("\nFrom: a <a@b>\nSubject: add d\n\n---\n"),
+            PatchFormat::Mbox
+        );
+        assert_eq!(
+            patch_format("Subject: [PATCH] add d\n\n"),
+            PatchFormat::Mbox
+        );
+    }
+
+    #[test]
+    fn detects_plain_diffs() {
+        let git_diff = "diff --git a/n.txt b/n.txt
+new file mode 100644
+index 0000000..3e75765
+--- /dev/null
++++ b/n.txt
+@@ -0,0 +1 @@
++new
+";
+        assert_eq!(patch_format(git_diff), PatchFormat::Diff);
+
+        let diff_u = "--- a/a.txt\t2026-10-17 21:00:40 +0000
++++ b/a.txt\t2026-10-17 21:00:40 +0000
+@@ -1,3 +1,4 @@
+ line1
++plain
+";
+        assert_eq!(patch_format(diff_u), PatchFormat::Diff);
+        assert_eq!(patch_format("Index: a.txt\n===\n"), PatchFormat::Diff);
+        assert_eq!(patch_format(""), PatchFormat::Diff);
+    }
 }
@@ -1,7 +1,14 @@
 pub mod backup;
 pub mod commands;
+pub mod config_file;
 pub mod flags;
+pub mod forge;
 pub mod git_commands;
+pub mod lockfile;
+pub mod output;
+pub mod patch_cache;
+pub mod rerere;
+pub mod state;
 pub mod types;
 pub mod utils;
 
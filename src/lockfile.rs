@@ -0,0 +1,86 @@
+use std::{fs, path::PathBuf};
+
+use anyhow::anyhow;
+use serde::{Deserialize, Serialize};
+
+use crate::{git_commands::GIT_ROOT, CONFIG_ROOT};
+
+pub static LOCKFILE: &str = "patchy.lock";
+
+/// Exact commits a run resolved to, so that `run --locked` can reproduce the same result
+#[derive(Serialize, Deserialize, Debug, Default)]
+#[serde(rename_all = "kebab-case")]
+pub struct Lockfile {
+    pub base: Option<LockedBase>,
+    #[serde(default)]
+    pub pull_requests: Vec<LockedPullRequest>,
+}
+
+/// Commit of `remote-branch` which the pull requests are merged into
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct LockedBase {
+    pub branch: String,
+    pub commit: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct LockedPullRequest {
+    /// The pull request as written in `pull-requests`, without its `@ <hash>`
+    pub number: String,
+    /// Head commit of the pull request
+    pub commit: String,
+    pub title: String,
+    pub url: String,
+    /// The pull request was merged or closed, so the run left it out
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub closed: bool,
+}
+
+impl Lockfile {
+    fn path() -> PathBuf {
+        GIT_ROOT.join(CONFIG_ROOT).join(LOCKFILE)
+    }
+
+    /// Reads `.patchy/patchy.lock`, if it exists
+    pub fn read() -> anyhow::Result<Option<Self>> {
+        let path = Self::path();
+
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let lockfile = fs::read_to_string(&path)?;
+
+        toml::from_str(&lockfile)
+            .map(Some)
+            .map_err(|err| anyhow!("Could not parse `{CONFIG_ROOT}/{LOCKFILE}`:\n{err}"))
+    }
+
+    pub fn write(&self) -> anyhow::Result<()> {
+        let contents = format!(
+            "# Generated by patchy, do not edit by hand.\n# Refresh it with `patchy update`\n\n{}",
+            toml::to_string_pretty(self)?
+        );
+
+        fs::write(Self::path(), contents)
+            .map_err(|err| anyhow!("Could not write `{CONFIG_ROOT}/{LOCKFILE}`\n{err}"))
+    }
+
+    pub fn pull_request(&self, number: &str) -> Option<&LockedPullRequest> {
+        self.pull_requests
+            .iter()
+            .find(|locked| locked.number == number)
+    }
+
+    /// Adds the pull request, or replaces the entry of the same pull request
+    pub fn lock_pull_request(&mut self, pull_request: LockedPullRequest) {
+        match self
+            .pull_requests
+            .iter_mut()
+            .find(|locked| locked.number == pull_request.number)
+        {
+            Some(locked) => *locked = pull_request,
+            None => self.pull_requests.push(pull_request),
+        }
+    }
+}
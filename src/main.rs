@@ -1,7 +1,12 @@
 use colored::Colorize;
-use patchy::commands::help::{HELP_FLAG, VERSION_FLAG};
-use patchy::commands::{gen_patch, help, init, pr_fetch, run};
+use patchy::commands::help::{HELP_FLAG, OUTPUT_FLAG, VERSION_FLAG};
+use patchy::commands::{
+    add, add_patch, gen_patch, help, init, pin, pr_fetch, remove, remove_patch, run, status, unpin,
+    update,
+};
 use patchy::fail;
+use patchy::flags::IS_JSON;
+use patchy::output::{is_reported, print_json, ErrorReport};
 use std::env;
 
 use patchy::types::CommandArgs;
@@ -14,6 +19,15 @@ async fn process_subcommand(subcommand: &str, args: CommandArgs) -> Result<()> {
         "init" => init(&args)?,
         "run" => run(&args).await?,
         "gen-patch" => gen_patch(&args)?,
+        "update" => update(&args).await?,
+        "status" => status(&args).await?,
+        // editing the config
+        "add" => add(&args).await?,
+        "remove" => remove(&args)?,
+        "pin" => pin(&args).await?,
+        "unpin" => unpin(&args)?,
+        "add-patch" => add_patch(&args)?,
+        "remove-patch" => remove_patch(&args)?,
         // lower level commands
         "pr-fetch" => pr_fetch(&args).await?,
         unrecognized => {
@@ -53,6 +67,19 @@ async fn main() -> Result<()> {
         args.insert(subcommand.clone());
     }
 
+    if *IS_JSON {
+        colored::control::set_override(false);
+    }
+
+    if let Some(output) = args
+        .iter()
+        .find_map(|arg| OUTPUT_FLAG.extract_from_arg(arg))
+        .filter(|output| !matches!(output.as_str(), "json" | "text"))
+    {
+        fail!("Unknown output format: {output}, expected json or text");
+        std::process::exit(1);
+    }
+
     if HELP_FLAG.is_in_args(&args) {
         help(Some(&subcommand))
     } else if VERSION_FLAG.is_in_args(&args) {
@@ -64,6 +91,11 @@ async fn main() -> Result<()> {
             Ok(()) => Ok(()),
             Err(msg) => {
                 fail!("{msg}");
+                if *IS_JSON && !is_reported() {
+                    print_json(&ErrorReport {
+                        error: msg.to_string(),
+                    });
+                }
                 std::process::exit(1);
             }
         }
@@ -0,0 +1,112 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use serde::Serialize;
+
+use crate::{lockfile::LockedBase, types::MergeStrategy};
+
+/// What happened to a pull request or a patch
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum Outcome {
+    /// Merged into the result, or would be in a dry run
+    Merged,
+    /// Left out since it was already merged into the repository
+    AlreadyMerged,
+    /// Left out since it couldn't be fetched or merged, or depends on one that couldn't
+    Skipped,
+    /// Left out since it is disabled in the config
+    Disabled,
+    /// Has merge conflicts which the user is resolving, with `--interactive`
+    Paused,
+    /// The run stopped before getting to it
+    NotAttempted,
+    /// Fetched into a local branch, with `pr-fetch`
+    Fetched,
+    /// A patch which was applied
+    Applied,
+    /// A patch file created with `gen-patch`
+    Created,
+    /// Could not be fetched, applied or created
+    Failed,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "kebab-case")]
+pub struct PullRequestReport {
+    pub number: String,
+    pub title: Option<String>,
+    pub url: Option<String>,
+    pub commit: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub strategy: Option<MergeStrategy>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub branch: Option<String>,
+    pub outcome: Outcome,
+    pub error: Option<String>,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "kebab-case")]
+pub struct PatchReport {
+    pub name: String,
+    /// The last commit of the patch
+    pub commit: Option<String>,
+    /// Every commit of the patch, as an mbox can have several
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub commits: Vec<String>,
+    pub outcome: Outcome,
+    pub error: Option<String>,
+}
+
+/// Report of `patchy run`
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "kebab-case")]
+pub struct RunReport {
+    pub dry_run: bool,
+    pub local_branch: String,
+    /// Whether `local-branch` was overwritten with the result
+    pub updated: bool,
+    /// URL of the remote the result was pushed to, with `--push`
+    pub pushed: Option<String>,
+    pub base: Option<LockedBase>,
+    pub pull_requests: Vec<PullRequestReport>,
+    pub patches: Vec<PatchReport>,
+}
+
+/// Report of `patchy pr-fetch`
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "kebab-case")]
+pub struct PrFetchReport {
+    pub pull_requests: Vec<PullRequestReport>,
+}
+
+/// Report of `patchy gen-patch`
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "kebab-case")]
+pub struct GenPatchReport {
+    pub patches: Vec<PatchReport>,
+}
+
+/// Report of a command which failed before it could finish
+#[derive(Serialize, Debug)]
+pub struct ErrorReport {
+    pub error: String,
+}
+
+/// Whether a report was printed, so that a command which fails after printing its report doesn't
+/// print a second one
+static REPORTED: AtomicBool = AtomicBool::new(false);
+
+pub fn is_reported() -> bool {
+    REPORTED.load(Ordering::Relaxed)
+}
+
+/// Prints a report to stdout
+pub fn print_json(report: &impl Serialize) {
+    REPORTED.store(true, Ordering::Relaxed);
+
+    match serde_json::to_string_pretty(report) {
+        Ok(json) => println!("{json}"),
+        Err(err) => eprintln!("Could not serialize the report\n{err}"),
+    }
+}
@@ -0,0 +1,271 @@
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::anyhow;
+use reqwest::Client;
+use sha2::{Digest, Sha256};
+
+use crate::{
+    git_commands::GIT_ROOT,
+    info, trace,
+    types::{Configuration, PatchSource},
+    utils::get_github_token,
+    APP_NAME, CONFIG_ROOT,
+};
+
+/// Directory inside of the config directory where patches downloaded from a URL are kept
+pub static PATCH_CACHE: &str = "cache";
+
+/// Path of each downloaded patch, by its URL
+pub type DownloadedPatches = HashMap<String, PathBuf>;
+
+/// The URL which serves a patch as a file
+///
+/// GitHub shows commits and comparisons between branches as web pages, but serves them as
+/// patches when `.patch` is added to their URL
+fn download_url(url: &str) -> String {
+    let url = url.split_once('#').map_or(url, |(url, _)| url);
+    let url = url.trim_end_matches('/');
+
+    let is_github_page = url
+        .strip_prefix("https://github.com/")
+        .is_some_and(|path| path.contains("/commit/") || path.contains("/compare/"));
+
+    if is_github_page && !url.ends_with(".patch") && !url.ends_with(".diff") {
+        format!("{url}.patch")
+    } else {
+        url.into()
+    }
+}
+
+/// Whether a URL is served by GitHub, which is the only host the GitHub token is sent to
+fn is_github_url(url: &str) -> bool {
+    ["https://github.com/", "https://raw.githubusercontent.com/"]
+        .iter()
+        .any(|host| url.starts_with(host))
+}
+
+fn sha256(contents: &[u8]) -> String {
+    Sha256::digest(contents)
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+/// Downloads a patch into the `cache` directory, in a file named after the sha256 of its contents
+///
+/// With a `pin`, the patch has to have that sha256. Since it can't change, the download is
+/// skipped if it is already in the cache
+///
+/// The GitHub token is only sent if the patch is on GitHub, so that private repositories can be
+/// downloaded from
+///
+/// Returns the path of the downloaded patch
+pub async fn download_patch(
+    client: &Client,
+    github_token: Option<&str>,
+    url: &str,
+    pin: Option<&str>,
+    cache: &Path,
+) -> anyhow::Result<PathBuf> {
+    let cached = |sha256: &str| cache.join(format!("{sha256}.patch"));
+
+    if let Some(pin) = pin {
+        if cached(pin).exists() {
+            trace!("Using patch {url} from the cache");
+            return Ok(cached(pin));
+        }
+    }
+
+    let download_url = download_url(url);
+    trace!("Downloading patch {url} from {download_url}");
+
+    let mut request = client.get(&download_url);
+    if let Some(token) = github_token.filter(|_| is_github_url(&download_url)) {
+        request = request.bearer_auth(token);
+    }
+
+    let response = request
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|err| anyhow!("Could not download patch {url}\n{err}"))?;
+
+    let contents = response
+        .bytes()
+        .await
+        .map_err(|err| anyhow!("Could not download patch {url}\n{err}"))?;
+
+    let sha256 = sha256(&contents);
+
+    if let Some(pin) = pin {
+        if sha256 != pin {
+            return Err(anyhow!(
+                "Patch {url} has sha256 {sha256}, but the config pins it to {pin}. \
+                If the new contents are expected, update its sha256"
+            ));
+        }
+    }
+
+    fs::create_dir_all(cache)
+        .map_err(|err| anyhow!("Could not create {CONFIG_ROOT}/{PATCH_CACHE}\n{err}"))?;
+
+    // Downloads can be fetched again, so they are kept out of the result
+    let gitignore = cache.join(".gitignore");
+    if !gitignore.exists() {
+        fs::write(&gitignore, "*\n")?;
+    }
+
+    fs::write(cached(&sha256), &contents)
+        .map_err(|err| anyhow!("Could not write patch {url} to the cache\n{err}"))?;
+
+    Ok(cached(&sha256))
+}
+
+/// The cache of the user's repository, which patches are kept in between runs
+pub fn patch_cache() -> PathBuf {
+    GIT_ROOT.join(CONFIG_ROOT).join(PATCH_CACHE)
+}
+
+/// Downloads every patch of the config which is a URL into the `cache` directory
+pub async fn download_patches(
+    config: &Configuration,
+    cache: &Path,
+) -> anyhow::Result<DownloadedPatches> {
+    let urls: Vec<_> = config
+        .patches
+        .iter()
+        .flatten()
+        .filter_map(|patch| match &patch.source {
+            PatchSource::Url { url, sha256 } => Some((url, sha256.as_deref())),
+            PatchSource::File(_) => None,
+        })
+        .collect();
+
+    let mut downloaded = DownloadedPatches::new();
+
+    if urls.is_empty() {
+        return Ok(downloaded);
+    }
+
+    info!("Downloading {} patches", urls.len());
+
+    // Patches are files rather than API responses, so the client keeps the default `Accept` header
+    let client = Client::builder()
+        .user_agent(APP_NAME)
+        .build()
+        .map_err(|err| anyhow!("Could not create HTTP client.\n{err}"))?;
+
+    let github_token = urls
+        .iter()
+        .any(|(url, _)| is_github_url(&download_url(url)))
+        .then(|| get_github_token(config.github_token.as_deref()))
+        .flatten();
+
+    for (url, pin) in urls {
+        downloaded.insert(
+            url.clone(),
+            download_patch(&client, github_token.as_deref(), url, pin, cache).await?,
+        );
+    }
+
+    Ok(downloaded)
+}
+
+/// Removes patches from the cache which no patch of the config downloads anymore
+pub fn remove_unused_patches(downloaded: &DownloadedPatches) -> anyhow::Result<()> {
+    let cache = patch_cache();
+
+    if !cache.exists() {
+        return Ok(());
+    }
+
+    let used: Vec<_> = downloaded
+        .values()
+        .filter_map(|path| path.file_name())
+        .collect();
+
+    for file in fs::read_dir(&cache)? {
+        let path = file?.path();
+        let is_unused = path
+            .extension()
+            .is_some_and(|extension| extension == "patch")
+            && path
+                .file_name()
+                .is_some_and(|file_name| !used.contains(&file_name));
+
+        if is_unused {
+            fs::remove_file(&path)?;
+            trace!("Removed unused patch {path:?} from the cache");
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn adds_patch_to_github_pages() {
+        for (url, expected) in [
+            (
+                "https://github.com/helix-editor/helix/commit/7bb8ec5a",
+                "https://github.com/helix-editor/helix/commit/7bb8ec5a.patch",
+            ),
+            (
+                "https://github.com/helix-editor/helix/commit/7bb8ec5a/",
+                "https://github.com/helix-editor/helix/commit/7bb8ec5a.patch",
+            ),
+            (
+                "https://github.com/helix-editor/helix/commit/7bb8ec5a#diff-04c6e90f",
+                "https://github.com/helix-editor/helix/commit/7bb8ec5a.patch",
+            ),
+            (
+                "https://github.com/helix-editor/helix/compare/master...someone:helix:feature-x",
+                "https://github.com/helix-editor/helix/compare/master...someone:helix:feature-x.patch",
+            ),
+        ] {
+            assert_eq!(download_url(url), expected, "{url}");
+        }
+    }
+
+    #[test]
+    fn keeps_other_urls() {
+        for url in [
+            "https://github.com/helix-editor/helix/commit/7bb8ec5a.patch",
+            "https://github.com/helix-editor/helix/commit/7bb8ec5a.diff",
+            "https://github.com/helix-editor/helix/pull/123",
+            "https://raw.githubusercontent.com/owner/repo/main/fix.patch",
+            "https://example.com/commit/fix.diff",
+            "https://gitlab.com/group/project/-/commit/7bb8ec5a",
+        ] {
+            assert_eq!(download_url(url), url, "{url}");
+        }
+    }
+
+    #[test]
+    fn sends_token_only_to_github() {
+        assert!(is_github_url(
+            "https://github.com/helix-editor/helix/commit/7bb8ec5a.patch"
+        ));
+        assert!(is_github_url(
+            "https://raw.githubusercontent.com/owner/repo/main/fix.patch"
+        ));
+        assert!(!is_github_url("https://example.com/fix.patch"));
+        assert!(!is_github_url("https://github.com.example.com/fix.patch"));
+        assert!(!is_github_url("http://github.com/owner/repo/fix.patch"));
+    }
+
+    #[test]
+    fn hashes_contents() {
+        assert_eq!(
+            sha256(b""),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+    }
+}
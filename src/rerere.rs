@@ -0,0 +1,96 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    git_commands::{git_work_dir, GIT, GIT_ROOT},
+    trace, CONFIG_ROOT,
+};
+
+/// Directory where git records conflict resolutions, and its copy inside of the config directory
+pub static RERERE_CACHE: &str = "rr-cache";
+
+/// Cache of conflict resolutions of the repository `GIT` currently runs in
+///
+/// It is shared between all worktrees of the repository. Git enables rerere by default once it exists
+pub fn git_rerere_cache() -> anyhow::Result<PathBuf> {
+    let common_dir = PathBuf::from(GIT(&["rev-parse", "--git-common-dir"])?);
+
+    Ok(git_work_dir().join(common_dir).join(RERERE_CACHE))
+}
+
+/// Copy of the conflict resolutions which is committed along with the config
+pub fn config_rerere_cache() -> PathBuf {
+    GIT_ROOT.join(CONFIG_ROOT).join(RERERE_CACHE)
+}
+
+/// Whether a recorded conflict also has its resolution recorded, rather than just the conflict
+fn is_resolved(entry: &Path) -> bool {
+    fs::read_dir(entry).is_ok_and(|mut files| {
+        files.any(|file| {
+            file.is_ok_and(|file| file.file_name().to_string_lossy().starts_with("postimage"))
+        })
+    })
+}
+
+/// Copies the resolved conflicts of one rerere cache into another one, without overwriting anything
+///
+/// Returns how many conflicts were copied
+fn copy_resolutions(from: &Path, to: &Path) -> anyhow::Result<usize> {
+    let Ok(entries) = fs::read_dir(from) else {
+        return Ok(0);
+    };
+
+    let mut copied = 0;
+
+    for entry in entries {
+        let entry = entry?.path();
+
+        let Some(conflict) = entry.file_name() else {
+            continue;
+        };
+
+        let destination = to.join(conflict);
+
+        if !entry.is_dir() || destination.exists() || !is_resolved(&entry) {
+            continue;
+        }
+
+        fs::create_dir_all(&destination)?;
+
+        for file in fs::read_dir(&entry)? {
+            let file = file?;
+            fs::copy(file.path(), destination.join(file.file_name()))?;
+        }
+
+        copied += 1;
+    }
+
+    Ok(copied)
+}
+
+/// Saves conflict resolutions recorded by git into the config directory, so they can be committed
+///
+/// Returns how many new resolutions were saved
+pub fn save_resolutions() -> anyhow::Result<usize> {
+    let saved = copy_resolutions(&git_rerere_cache()?, &config_rerere_cache())?;
+
+    trace!("Saved {saved} conflict resolutions to {CONFIG_ROOT}/{RERERE_CACHE}");
+
+    Ok(saved)
+}
+
+/// Makes the conflict resolutions of the config directory available to git
+///
+/// The cache is only created if there is a resolution to copy into it, since its existence enables
+/// rerere for all of the user's own merges as well. patchy's own commands enable rerere with `-c`
+pub fn load_resolutions() -> anyhow::Result<()> {
+    let cache = git_rerere_cache()?;
+
+    let loaded = copy_resolutions(&config_rerere_cache(), &cache)?;
+
+    trace!("Loaded {loaded} conflict resolutions from {CONFIG_ROOT}/{RERERE_CACHE}");
+
+    Ok(())
+}
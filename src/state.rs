@@ -0,0 +1,92 @@
+use std::{collections::HashMap, fs, path::PathBuf};
+
+use anyhow::anyhow;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    git_commands::{GIT, GIT_ROOT},
+    lockfile::Lockfile,
+    types::{BranchAndRemote, GitHubResponse},
+    APP_NAME,
+};
+
+/// A pull request which was fetched, or the reason why it couldn't be
+///
+/// Its branch is `None` when it is closed, since it is left out of the run
+pub type Fetched = Result<(GitHubResponse, Option<BranchAndRemote>), String>;
+
+/// What happened to each pull request and patch during a run
+pub type Report = Vec<Result<String, String>>;
+
+/// Why each pull request which wasn't merged was left out, by its number
+pub type Failed = HashMap<String, String>;
+
+/// Pull request whose merge has conflicts that the user is resolving
+#[derive(Serialize, Deserialize, Debug)]
+pub struct PausedMerge {
+    pub pull_request: String,
+    pub response: GitHubResponse,
+    pub info: BranchAndRemote,
+}
+
+/// A run paused by `--interactive`, stored until it is continued, skipped or aborted
+#[derive(Serialize, Deserialize, Debug)]
+pub struct RunState {
+    /// Worktree in which the merge with conflicts is waiting to be resolved
+    pub worktree: PathBuf,
+    /// Branch and remote of the repository the pull requests are merged into
+    pub base: BranchAndRemote,
+    pub paused: PausedMerge,
+    /// Pull requests which still need to be merged after the paused one, in order
+    pub remaining: Vec<(String, Fetched)>,
+    pub report: Report,
+    #[serde(default)]
+    pub failed: Failed,
+    /// Commits of the base and the pull requests fetched so far
+    pub lockfile: Lockfile,
+}
+
+impl RunState {
+    /// Where the state is kept, `.git/patchy/state.json`
+    fn path() -> anyhow::Result<PathBuf> {
+        let common_dir = PathBuf::from(GIT(&["rev-parse", "--git-common-dir"])?);
+
+        Ok(GIT_ROOT.join(common_dir).join(APP_NAME).join("state.json"))
+    }
+
+    /// Loads the paused run, if there is one
+    pub fn load() -> anyhow::Result<Option<Self>> {
+        let path = Self::path()?;
+
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let state = fs::read_to_string(&path)?;
+
+        serde_json::from_str(&state)
+            .map(Some)
+            .map_err(|err| anyhow!("Could not read the state of the paused run at {path:?}\n{err}"))
+    }
+
+    pub fn save(&self) -> anyhow::Result<()> {
+        let path = Self::path()?;
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        fs::write(&path, serde_json::to_string_pretty(self)?)
+            .map_err(|err| anyhow!("Could not save the state of the run to {path:?}\n{err}"))
+    }
+
+    pub fn remove() -> anyhow::Result<()> {
+        let path = Self::path()?;
+
+        if path.exists() {
+            fs::remove_file(path)?;
+        }
+
+        Ok(())
+    }
+}
@@ -1,7 +1,7 @@
-use std::collections::HashSet;
-
 use indexmap::IndexSet;
-use serde::{Deserialize, Serialize};
+use serde::{de, Deserialize, Deserializer, Serialize};
+
+use crate::{forge::Forge, utils::pull_request_label};
 
 pub type CommandArgs = IndexSet<String>;
 
@@ -9,43 +9,674 @@ pub type CommandArgs = IndexSet<String>;
 #[serde(rename_all = "kebab-case")]
 pub struct Configuration {
     pub local_branch: String,
-    pub patches: Option<HashSet<String>>,
-    pub pull_requests: Vec<String>,
+    /// Applied in this order, after the pull requests
+    pub patches: Option<Vec<PatchEntry>>,
+    pub pull_requests: Vec<PullRequestEntry>,
     pub remote_branch: String,
     pub repo: String,
+    /// Needed when `repo` is the URL of a self-hosted forge whose kind can't be guessed from its host
+    pub forge: Option<Forge>,
+    pub github_token: Option<String>,
+    #[serde(default)]
+    pub transport: Transport,
+    pub jobs: Option<usize>,
+    /// How pull requests are merged, unless they have a `strategy` of their own
+    #[serde(default)]
+    pub strategy: MergeStrategy,
+    /// Remote which `--push` pushes the result to, `origin` by default
+    pub push_remote: Option<String>,
+    /// Branch of `push-remote` which `--push` overwrites, `local-branch` by default
+    pub push_branch: Option<String>,
+    /// Whether patches fall back to a three-way merge, unless they have a `three-way` of their own
+    #[serde(default)]
+    pub patch_three_way: bool,
+    /// How patches handle whitespace, unless they have a `whitespace` of their own
+    pub patch_whitespace: Option<PatchWhitespace>,
+    /// What happens when a patch doesn't apply, unless it has an `on-failure` of its own
+    #[serde(default)]
+    pub patch_on_failure: PatchFailure,
+}
+
+impl Configuration {
+    /// The remote and branch which `--push` pushes the result to
+    pub fn push_target(&self) -> (&str, &str) {
+        (
+            self.push_remote.as_deref().unwrap_or("origin"),
+            self.push_branch.as_deref().unwrap_or(&self.local_branch),
+        )
+    }
+
+    /// How a pull request is merged
+    pub fn merge_strategy(&self, pull_request: &str) -> MergeStrategy {
+        self.pull_requests
+            .iter()
+            .find(|entry| entry.number == pull_request)
+            .and_then(|entry| entry.strategy)
+            .unwrap_or(self.strategy)
+    }
+
+    /// How a patch is applied
+    pub fn apply_options(&self, patch: &PatchEntry) -> ApplyOptions {
+        ApplyOptions {
+            three_way: patch.three_way.unwrap_or(self.patch_three_way),
+            whitespace: patch.whitespace.or(self.patch_whitespace),
+            on_failure: patch.on_failure.unwrap_or(self.patch_on_failure),
+        }
+    }
+
+    /// The pull requests in the order they are merged in: the order of the config, except that
+    /// each one comes after the pull requests it depends on
+    pub fn ordered_pull_requests(&self) -> anyhow::Result<Vec<&PullRequestEntry>> {
+        for entry in &self.pull_requests {
+            if let Some(missing) = entry.depends_on.iter().find(|dependency| {
+                !self
+                    .pull_requests
+                    .iter()
+                    .any(|other| &other.number == *dependency)
+            }) {
+                return Err(anyhow::anyhow!(
+                    "{} depends on {}, which is not in pull-requests",
+                    pull_request_label(&entry.number),
+                    pull_request_label(missing)
+                ));
+            }
+        }
+
+        let mut ordered: Vec<&PullRequestEntry> = vec![];
+        let mut remaining: Vec<&PullRequestEntry> = self.pull_requests.iter().collect();
+
+        while !remaining.is_empty() {
+            let Some(next) = remaining.iter().position(|entry| {
+                entry
+                    .depends_on
+                    .iter()
+                    .all(|dependency| ordered.iter().any(|merged| &merged.number == dependency))
+            }) else {
+                return Err(anyhow::anyhow!(
+                    "Could not order pull requests {}, since their `depends-on` form a cycle",
+                    remaining
+                        .iter()
+                        .map(|entry| pull_request_label(&entry.number))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                ));
+            };
+
+            ordered.push(remaining.remove(next));
+        }
+
+        Ok(ordered)
+    }
+}
+
+/// A pull request in `pull-requests`, written either as `"123 @ <commit>"` or as an inline table
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PullRequestEntry {
+    /// How the entry is referred to in `depends-on`, the lockfile and messages: the number of a pull
+    /// request of `repo`, `owner/repo#123` for a pull request of another repository, or
+    /// `<remote>:<branch>` for a branch
+    pub number: String,
+    pub source: Source,
+    /// Commit to use instead of the latest one
+    pub commit: Option<String>,
+    /// Name of the local branch the pull request is fetched into
+    pub branch: Option<String>,
+    pub strategy: Option<MergeStrategy>,
+    /// Disabled pull requests stay in the config, but are left out of the run
+    pub enabled: bool,
+    /// Why the pull request is in the config, for the user's own reference
+    pub note: Option<String>,
+    /// Pull requests which have to be merged before this one
+    pub depends_on: Vec<String>,
+}
+
+/// Where an entry of `pull-requests` is fetched from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Source {
+    /// A pull request of `repo`
+    PullRequest,
+    /// A pull request of another repository on the same forge as `repo`, such as `owner/other-repo#123`
+    OtherRepo { repo: String, number: String },
+    /// A branch of any repository, which doesn't need to have a pull request
+    Branch { remote: String, branch: String },
+}
+
+impl PullRequestEntry {
+    /// Parses the short form of a pull request, such as `#123`, `123 @ <commit>` or `owner/repo#123`
+    pub fn parse(pull_request: &str) -> Result<Self, String> {
+        let (number, commit) = match pull_request.split_once('@') {
+            Some((number, commit)) => (number, Some(commit.trim())),
+            None => (pull_request, None),
+        };
+
+        if commit.is_some_and(str::is_empty) {
+            return Err(format!(
+                "\"{pull_request}\" is missing a commit after the @, expected for example \"123 @ <commit>\""
+            ));
+        }
+
+        let (number, source) = parse_pull_request(number.trim())?;
+
+        Ok(Self {
+            number,
+            source,
+            commit: commit.map(String::from),
+            branch: None,
+            strategy: None,
+            enabled: true,
+            note: None,
+            depends_on: vec![],
+        })
+    }
+}
+
+impl PullRequestEntry {
+    /// Parses a pull request given on the command line: its short form, or `<remote>:<branch>`
+    /// for a branch, each optionally followed by `@ <commit>`
+    pub fn parse_argument(arg: &str) -> Result<Self, String> {
+        // The remote of a branch may have an @ of its own, as in `git@github.com:owner/repo`
+        let (key, commit) = match arg.rsplit_once('@') {
+            Some((key, commit))
+                if commit.trim().len() >= 4
+                    && commit.trim().chars().all(|ch| ch.is_ascii_hexdigit()) =>
+            {
+                (key.trim(), Some(commit.trim()))
+            }
+            _ => (arg.trim(), None),
+        };
+
+        match key.rsplit_once(':') {
+            Some((remote, branch))
+                if !remote.is_empty() && !branch.is_empty() && !branch.starts_with('/') =>
+            {
+                Ok(Self {
+                    number: key.into(),
+                    source: Source::Branch {
+                        remote: remote.into(),
+                        branch: branch.into(),
+                    },
+                    commit: commit.map(String::from),
+                    branch: None,
+                    strategy: None,
+                    enabled: true,
+                    note: None,
+                    depends_on: vec![],
+                })
+            }
+            _ => Self::parse(arg),
+        }
+    }
+
+    /// Whether this is the same pull request as `other`, regardless of its options
+    pub fn is_same(&self, other: &Self) -> bool {
+        self.number == other.number && self.source == other.source
+    }
+}
+
+/// Parses `123`, `#123` or `owner/repo#123` into how the pull request is referred to, and where it is from
+fn parse_pull_request(pull_request: &str) -> Result<(String, Source), String> {
+    match pull_request.rsplit_once('#') {
+        Some((repo, number)) if !repo.is_empty() => {
+            let number = parse_number(number)?;
+            let repo = repo.trim_end_matches('/');
+            Ok((
+                format!("{repo}#{number}"),
+                Source::OtherRepo {
+                    repo: repo.into(),
+                    number,
+                },
+            ))
+        }
+        _ => Ok((
+            parse_number(pull_request.strip_prefix('#').unwrap_or(pull_request))?,
+            Source::PullRequest,
+        )),
+    }
+}
+
+fn parse_number(number: &str) -> Result<String, String> {
+    if number.is_empty() || !number.chars().all(|ch| ch.is_ascii_digit()) {
+        return Err(format!(
+            "\"{number}\" is not a pull request number, expected for example \"123\" or \"123 @ <commit>\""
+        ));
+    }
+
+    Ok(number.into())
+}
+
+/// The number of a pull request in the table form, either `number = 123` or `number = "123"`
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum PullRequestNumber {
+    Integer(u64),
+    String(String),
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+struct PullRequestTable {
+    number: Option<PullRequestNumber>,
+    /// Repository of the pull request, when it isn't `repo`
+    repo: Option<String>,
+    /// URL of a repository to fetch `branch` from, for a branch which isn't a pull request
+    remote: Option<String>,
+    commit: Option<String>,
+    branch: Option<String>,
+    strategy: Option<MergeStrategy>,
+    #[serde(default = "enabled_by_default")]
+    enabled: bool,
+    note: Option<String>,
+    #[serde(default)]
+    depends_on: Vec<PullRequestNumber>,
+}
+
+impl PullRequestNumber {
+    fn parse(self) -> Result<(String, Source), String> {
+        match self {
+            Self::Integer(number) => Ok((number.to_string(), Source::PullRequest)),
+            Self::String(number) => parse_pull_request(number.trim()),
+        }
+    }
+
+    /// How the pull request is referred to, where branches are `<remote>:<branch>`
+    fn dependency(self) -> Result<String, String> {
+        match self {
+            Self::String(branch) if branch.contains(':') => Ok(branch),
+            number => number.parse().map(|(number, _)| number),
+        }
+    }
+}
+
+impl PullRequestTable {
+    /// How the entry is referred to, where it is from, and the local branch it is fetched into
+    fn source(&mut self) -> Result<(String, Source, Option<String>), String> {
+        match (self.remote.take(), self.number.take()) {
+            (Some(_), Some(_)) => Err(
+                "a pull request can't have both a `number` and a `remote`, since `remote` is for branches without a pull request".into(),
+            ),
+            (Some(remote), None) => {
+                let Some(branch) = self.branch.take() else {
+                    return Err(format!(
+                        "{{ remote = \"{remote}\" }} is missing the `branch` to fetch from it"
+                    ));
+                };
+
+                if self.repo.is_some() {
+                    return Err(format!(
+                        "{{ remote = \"{remote}\" }} can't have a `repo`, since `remote` is already the repository"
+                    ));
+                }
+
+                Ok((
+                    format!("{remote}:{branch}"),
+                    Source::Branch { remote, branch },
+                    None,
+                ))
+            }
+            (None, Some(number)) => {
+                let (number, source) = match (self.repo.take(), number.parse()?) {
+                    (Some(repo), (number, Source::PullRequest)) => {
+                        parse_pull_request(&format!("{repo}#{number}"))?
+                    }
+                    (Some(_), (number, _)) => {
+                        return Err(format!(
+                            "pull request {number} has a `repo` even though its number already names one"
+                        ))
+                    }
+                    (None, parsed) => parsed,
+                };
+
+                Ok((number, source, self.branch.take()))
+            }
+            (None, None) => Err(
+                "a pull request needs a `number`, or a `remote` and a `branch` to merge a branch without a pull request".into(),
+            ),
+        }
+    }
+}
+
+fn enabled_by_default() -> bool {
+    true
+}
+
+impl<'de> Deserialize<'de> for PullRequestEntry {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct Visitor;
+
+        impl<'de> de::Visitor<'de> for Visitor {
+            type Value = PullRequestEntry;
+
+            fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+                formatter.write_str(
+                    "a pull request such as \"123\", \"owner/repo#123 @ <commit>\", { number = 123, commit = \"<commit>\" } or { remote = \"<url>\", branch = \"<branch>\" }",
+                )
+            }
+
+            fn visit_u64<E: de::Error>(self, number: u64) -> Result<Self::Value, E> {
+                PullRequestEntry::parse(&number.to_string()).map_err(E::custom)
+            }
+
+            fn visit_i64<E: de::Error>(self, number: i64) -> Result<Self::Value, E> {
+                PullRequestEntry::parse(&number.to_string()).map_err(E::custom)
+            }
+
+            fn visit_str<E: de::Error>(self, pull_request: &str) -> Result<Self::Value, E> {
+                PullRequestEntry::parse(pull_request).map_err(E::custom)
+            }
+
+            fn visit_map<A: de::MapAccess<'de>>(self, map: A) -> Result<Self::Value, A::Error> {
+                let mut table =
+                    PullRequestTable::deserialize(de::value::MapAccessDeserializer::new(map))?;
+
+                let (number, source, branch) = table.source().map_err(de::Error::custom)?;
+
+                let depends_on = table
+                    .depends_on
+                    .into_iter()
+                    .map(PullRequestNumber::dependency)
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(de::Error::custom)?;
+
+                if table
+                    .commit
+                    .as_deref()
+                    .is_some_and(|commit| commit.trim().is_empty())
+                {
+                    return Err(de::Error::custom(format!(
+                        "the commit of {} is empty",
+                        pull_request_label(&number)
+                    )));
+                }
+
+                Ok(PullRequestEntry {
+                    number,
+                    source,
+                    commit: table.commit,
+                    branch,
+                    strategy: table.strategy,
+                    enabled: table.enabled,
+                    note: table.note,
+                    depends_on,
+                })
+            }
+        }
+
+        deserializer.deserialize_any(Visitor)
+    }
+}
+
+/// A patch in `patches`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PatchEntry {
+    pub source: PatchSource,
+    /// Author of the commit made for a plain diff as `Name <email>`, since a diff has no author of its own
+    pub author: Option<String>,
+    /// Message of the commit made for a plain diff
+    pub message: Option<String>,
+    pub three_way: Option<bool>,
+    pub whitespace: Option<PatchWhitespace>,
+    pub on_failure: Option<PatchFailure>,
+}
+
+/// Where a patch comes from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PatchSource {
+    /// Name of a file in the config directory, without `.patch`
+    File(String),
+    /// URL to download the patch from, optionally pinned to the sha256 of its contents
+    Url { url: String, sha256: Option<String> },
+}
+
+/// A patch written as a table, such as `{ url = "https://…", sha256 = "…" }`
+#[derive(Deserialize, Default)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+struct PatchOptions {
+    name: Option<String>,
+    url: Option<String>,
+    sha256: Option<String>,
+    author: Option<String>,
+    message: Option<String>,
+    three_way: Option<bool>,
+    whitespace: Option<PatchWhitespace>,
+    on_failure: Option<PatchFailure>,
+}
+
+impl TryFrom<PatchOptions> for PatchEntry {
+    type Error = String;
+
+    fn try_from(options: PatchOptions) -> Result<Self, Self::Error> {
+        let source = match (options.name, options.url) {
+            (Some(name), None) => {
+                if options.sha256.is_some() {
+                    return Err(format!(
+                        "patch {name} can't have a `sha256`, since only patches downloaded from a `url` can change"
+                    ));
+                }
+                PatchSource::File(name)
+            }
+            (None, Some(url)) => {
+                if !is_url(&url) {
+                    return Err(format!(
+                        "\"{url}\" is not a URL, patches in the config directory are written as `name`"
+                    ));
+                }
+
+                if let Some(sha256) = &options.sha256 {
+                    if sha256.len() != 64 || !sha256.chars().all(|ch| ch.is_ascii_hexdigit()) {
+                        return Err(format!(
+                            "the sha256 of patch {url} should be 64 hexadecimal characters, not \"{sha256}\""
+                        ));
+                    }
+                }
+
+                PatchSource::Url {
+                    url,
+                    sha256: options.sha256.map(|sha256| sha256.to_ascii_lowercase()),
+                }
+            }
+            (Some(_), Some(_)) => {
+                return Err("a patch has either a `name` or a `url`, not both".into())
+            }
+            (None, None) => {
+                return Err(
+                    "a patch needs the `name` of a file in the config directory, or a `url`".into(),
+                )
+            }
+        };
+
+        Ok(Self {
+            source,
+            author: options.author,
+            message: options.message,
+            three_way: options.three_way,
+            whitespace: options.whitespace,
+            on_failure: options.on_failure,
+        })
+    }
+}
+
+impl<'de> Deserialize<'de> for PatchEntry {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct Visitor;
+
+        impl<'de> de::Visitor<'de> for Visitor {
+            type Value = PatchEntry;
+
+            fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+                formatter.write_str(
+                    "a patch such as \"my-patch\", \"https://…\" or { url = \"https://…\", sha256 = \"…\" }",
+                )
+            }
+
+            fn visit_str<E: de::Error>(self, patch: &str) -> Result<Self::Value, E> {
+                let options = if is_url(patch) {
+                    PatchOptions {
+                        url: Some(patch.into()),
+                        ..Default::default()
+                    }
+                } else {
+                    PatchOptions {
+                        name: Some(patch.into()),
+                        ..Default::default()
+                    }
+                };
+
+                PatchEntry::try_from(options).map_err(E::custom)
+            }
+
+            // Unlike an untagged enum, this lets the error of a bad key or value in the table through
+            fn visit_map<A: de::MapAccess<'de>>(self, map: A) -> Result<Self::Value, A::Error> {
+                let options =
+                    PatchOptions::deserialize(de::value::MapAccessDeserializer::new(map))?;
+
+                PatchEntry::try_from(options).map_err(de::Error::custom)
+            }
+        }
+
+        deserializer.deserialize_any(Visitor)
+    }
+}
+
+fn is_url(patch: &str) -> bool {
+    patch.starts_with("https://") || patch.starts_with("http://")
+}
+
+impl std::fmt::Display for PatchEntry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.source {
+            PatchSource::File(name) => f.write_str(name),
+            PatchSource::Url { url, .. } => f.write_str(url),
+        }
+    }
+}
+
+/// How a patch is applied, from its own options and those of the config
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ApplyOptions {
+    /// Fall back to a three-way merge when the patch doesn't apply cleanly, like `git am --3way`
+    pub three_way: bool,
+    pub whitespace: Option<PatchWhitespace>,
+    pub on_failure: PatchFailure,
+}
+
+/// How whitespace is handled when applying a patch
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum PatchWhitespace {
+    /// Fix trailing whitespace and the like in the lines the patch adds, like `--whitespace=fix`
+    Fix,
+    /// Apply the patch even if the whitespace of the lines around its changes differs, like
+    /// `--ignore-whitespace`
+    Ignore,
+}
+
+/// What happens when a patch doesn't apply
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum PatchFailure {
+    /// Stop the run, leaving `local-branch` as it was
+    #[default]
+    Abort,
+    /// Leave the patch out and carry on with the next one
+    Skip,
+}
+
+/// How a pull request is merged into the result
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum MergeStrategy {
+    /// All commits of the pull request become a single one
+    #[default]
+    Squash,
+    /// A merge commit, keeping the commits of the pull request
+    Merge,
+    /// Each commit of the pull request is cherry-picked
+    Rebase,
+    /// A single merge commit for consecutive pull requests which all use this strategy
+    Octopus,
 }
 
+impl std::fmt::Display for MergeStrategy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Squash => "squash",
+            Self::Merge => "merge",
+            Self::Rebase => "rebase",
+            Self::Octopus => "octopus",
+        })
+    }
+}
+
+/// Protocol used to fetch from remote repositories
+#[derive(Deserialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Transport {
+    #[default]
+    Https,
+    /// Needed for private repositories when HTTPS credentials are not configured for git
+    Ssh,
+}
+
+impl Transport {
+    /// Picks the URL matching this transport out of a repository returned by the API
+    pub fn clone_url<'a>(&self, repo: &'a Repo) -> &'a str {
+        match self {
+            Transport::Https => &repo.clone_url,
+            Transport::Ssh => &repo.ssh_url,
+        }
+    }
+}
+
+/// A pull request as returned by the GitHub API. Other forges' responses are converted into it
 #[derive(Serialize, Deserialize, Debug)]
 pub struct GitHubResponse {
     pub head: Head,
     pub title: String,
     pub html_url: String,
+    pub state: PullRequestState,
+    /// Merged pull requests are closed as well
+    #[serde(default)]
+    pub merged: bool,
+    /// `None` while the forge is still working out whether it can be merged
+    #[serde(default)]
+    pub mergeable: Option<bool>,
+    pub updated_at: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum PullRequestState {
+    Open,
+    Closed,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct Head {
     pub repo: Repo,
     pub r#ref: String,
+    /// Latest commit of the pull request
+    pub sha: String,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct Repo {
     pub clone_url: String,
+    pub ssh_url: String,
 }
 
-#[derive(Debug)]
+#[derive(Serialize, Deserialize, Debug)]
 pub struct Branch {
     pub local_branch_name: String,
     pub upstream_branch_name: String,
 }
 
-#[derive(Debug)]
+#[derive(Serialize, Deserialize, Debug)]
 pub struct Remote {
     pub local_remote_alias: String,
     pub repository_url: String,
 }
 
-#[derive(Debug)]
+#[derive(Serialize, Deserialize, Debug)]
 pub struct BranchAndRemote {
     pub branch: Branch,
     pub remote: Remote,
@@ -69,3 +700,333 @@ impl BranchAndRemote {
         Self { branch, remote }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Deserialize)]
+    #[serde(rename_all = "kebab-case")]
+    struct PullRequests {
+        pull_requests: Vec<PullRequestEntry>,
+    }
+
+    /// Parses entries of `pull-requests` the way the config does
+    fn parse(entries: &str) -> Result<Vec<PullRequestEntry>, String> {
+        toml::from_str::<PullRequests>(&format!("pull-requests = [{entries}]"))
+            .map(|config| config.pull_requests)
+            .map_err(|err| err.message().to_owned())
+    }
+
+    fn parse_one(entry: &str) -> PullRequestEntry {
+        parse(entry).unwrap().remove(0)
+    }
+
+    #[test]
+    fn parses_short_forms() {
+        for entry in ["123", "\"123\"", "\"#123\"", "\" 123 \""] {
+            let parsed = parse_one(entry);
+            assert_eq!(parsed.number, "123", "{entry}");
+            assert_eq!(parsed.source, Source::PullRequest, "{entry}");
+            assert_eq!(parsed.commit, None, "{entry}");
+            assert!(parsed.enabled, "{entry}");
+        }
+
+        let pinned = parse_one("\"123 @ abc123\"");
+        assert_eq!(pinned.number, "123");
+        assert_eq!(pinned.commit.as_deref(), Some("abc123"));
+
+        let other_repo = parse_one("\"owner/repo#5@abc123\"");
+        assert_eq!(other_repo.number, "owner/repo#5");
+        assert_eq!(
+            other_repo.source,
+            Source::OtherRepo {
+                repo: "owner/repo".into(),
+                number: "5".into()
+            }
+        );
+        assert_eq!(other_repo.commit.as_deref(), Some("abc123"));
+    }
+
+    #[test]
+    fn rejects_invalid_short_forms() {
+        assert!(parse("\"abc\"")
+            .unwrap_err()
+            .contains("\"abc\" is not a pull request number"));
+        assert!(parse("\"123 @ \"")
+            .unwrap_err()
+            .contains("is missing a commit after the @"));
+        assert!(parse("\"owner/repo#x\"")
+            .unwrap_err()
+            .contains("\"x\" is not a pull request number"));
+        assert!(parse("\"\"")
+            .unwrap_err()
+            .contains("is not a pull request number"));
+    }
+
+    #[test]
+    fn parses_tables() {
+        let entry = parse_one(
+            r##"{ number = 123, commit = "abc123", branch = "mine", strategy = "merge", enabled = false, note = "for me", depends-on = [1, "#2", "owner/repo#3", "fork:fix"] }"##,
+        );
+        assert_eq!(
+            entry,
+            PullRequestEntry {
+                number: "123".into(),
+                source: Source::PullRequest,
+                commit: Some("abc123".into()),
+                branch: Some("mine".into()),
+                strategy: Some(MergeStrategy::Merge),
+                enabled: false,
+                note: Some("for me".into()),
+                depends_on: vec![
+                    "1".into(),
+                    "2".into(),
+                    "owner/repo#3".into(),
+                    "fork:fix".into()
+                ],
+            }
+        );
+
+        assert_eq!(parse_one(r##"{ number = "#7" }"##).number, "7");
+
+        for entry in [
+            r##"{ number = "owner/other#5" }"##,
+            r##"{ number = 5, repo = "owner/other" }"##,
+            r##"{ number = "5", repo = "owner/other/" }"##,
+        ] {
+            let parsed = parse_one(entry);
+            assert_eq!(parsed.number, "owner/other#5", "{entry}");
+            assert_eq!(
+                parsed.source,
+                Source::OtherRepo {
+                    repo: "owner/other".into(),
+                    number: "5".into()
+                },
+                "{entry}"
+            );
+        }
+
+        let branch = parse_one(r##"{ remote = "https://example.com/fork.git", branch = "fix" }"##);
+        assert_eq!(branch.number, "https://example.com/fork.git:fix");
+        assert_eq!(
+            branch.source,
+            Source::Branch {
+                remote: "https://example.com/fork.git".into(),
+                branch: "fix".into()
+            }
+        );
+        // For a branch, `branch` is the one to fetch rather than the local one
+        assert_eq!(branch.branch, None);
+    }
+
+    #[test]
+    fn rejects_invalid_tables() {
+        for (entry, error) in [
+            (
+                r##"{ number = 1, remote = "fork", branch = "fix" }"##,
+                "a pull request can't have both a `number` and a `remote`",
+            ),
+            (
+                r##"{ remote = "fork" }"##,
+                "{ remote = \"fork\" } is missing the `branch` to fetch from it",
+            ),
+            (
+                r##"{ remote = "fork", branch = "fix", repo = "owner/repo" }"##,
+                "{ remote = \"fork\" } can't have a `repo`",
+            ),
+            (
+                r##"{ number = "owner/a#1", repo = "owner/b" }"##,
+                "pull request owner/a#1 has a `repo` even though its number already names one",
+            ),
+            (
+                r##"{ commit = "abc123" }"##,
+                "a pull request needs a `number`",
+            ),
+            (
+                r##"{ number = 1, commit = " " }"##,
+                "the commit of #1 is empty",
+            ),
+            (
+                r##"{ number = "one" }"##,
+                "\"one\" is not a pull request number",
+            ),
+            (
+                r##"{ number = 1, depends-on = ["two"] }"##,
+                "\"two\" is not a pull request number",
+            ),
+            (
+                r##"{ number = 1, colour = "red" }"##,
+                "unknown field `colour`",
+            ),
+            (
+                r##"{ number = 1, strategy = "fast" }"##,
+                "unknown variant `fast`",
+            ),
+        ] {
+            let err = parse(entry).unwrap_err();
+            assert!(err.contains(error), "{entry}: {err}");
+        }
+    }
+
+    #[test]
+    fn parses_arguments() {
+        let branch =
+            PullRequestEntry::parse_argument("git@github.com:someone/helix.git:fix/typo").unwrap();
+        assert_eq!(branch.number, "git@github.com:someone/helix.git:fix/typo");
+        assert_eq!(
+            branch.source,
+            Source::Branch {
+                remote: "git@github.com:someone/helix.git".into(),
+                branch: "fix/typo".into()
+            }
+        );
+        assert_eq!(branch.commit, None);
+
+        let pinned =
+            PullRequestEntry::parse_argument("https://example.com/fork.git:fix@a556aeef").unwrap();
+        assert_eq!(pinned.number, "https://example.com/fork.git:fix");
+        assert_eq!(pinned.commit.as_deref(), Some("a556aeef"));
+
+        // Written the same way as in the config, the entries are the same
+        for (arg, entry) in [
+            ("#12", "12"),
+            ("12@abcdef", "\"12 @ abc\""),
+            ("owner/repo#5", "{ number = 5, repo = \"owner/repo\" }"),
+            (
+                "fork:fix",
+                "{ remote = \"fork\", branch = \"fix\", note = \"mine\" }",
+            ),
+        ] {
+            let arg = PullRequestEntry::parse_argument(arg).unwrap();
+            assert!(arg.is_same(&parse_one(entry)), "{entry}");
+        }
+
+        assert!(!PullRequestEntry::parse_argument("5")
+            .unwrap()
+            .is_same(&parse_one("\"owner/repo#5\"")));
+        assert!(!PullRequestEntry::parse_argument("a:fix")
+            .unwrap()
+            .is_same(&parse_one("{ remote = \"b\", branch = \"fix\" }")));
+
+        assert!(
+            PullRequestEntry::parse_argument("https://example.com/fork.git")
+                .unwrap_err()
+                .contains("is not a pull request number")
+        );
+    }
+
+    /// A config with the given entries of `pull-requests`
+    fn config(entries: &str) -> Configuration {
+        toml::from_str(&format!(
+            r##"
+            repo = "owner/repo"
+            remote-branch = "main"
+            local-branch = "patchy"
+            pull-requests = [{entries}]
+            "##
+        ))
+        .unwrap()
+    }
+
+    fn order(entries: &str) -> anyhow::Result<Vec<String>> {
+        Ok(config(entries)
+            .ordered_pull_requests()?
+            .into_iter()
+            .map(|entry| entry.number.clone())
+            .collect())
+    }
+
+    #[test]
+    fn keeps_config_order_without_dependencies() {
+        assert_eq!(order("3, 1, 2").unwrap(), ["3", "1", "2"]);
+    }
+
+    #[test]
+    fn orders_pull_requests_after_their_dependencies() {
+        assert_eq!(
+            order(r##"{ number = 3, depends-on = [1] }, 1, 2"##).unwrap(),
+            ["1", "3", "2"]
+        );
+        assert_eq!(
+            order(
+                r##"{ number = 1, depends-on = ["owner/other#2"] }, { number = "owner/other#2", depends-on = ["fork:fix"] }, { remote = "fork", branch = "fix" }"##
+            )
+            .unwrap(),
+            ["fork:fix", "owner/other#2", "1"]
+        );
+    }
+
+    #[test]
+    fn rejects_missing_dependencies() {
+        let err = order(r##"1, { number = 3, depends-on = [1, 9] }"##)
+            .unwrap_err()
+            .to_string();
+        assert_eq!(err, "#3 depends on #9, which is not in pull-requests");
+    }
+
+    #[test]
+    fn rejects_dependency_cycles() {
+        let err = order(
+            r##"4, { number = 1, depends-on = [2] }, { number = 2, depends-on = [3] }, { number = 3, depends-on = [1] }"##,
+        )
+        .unwrap_err()
+        .to_string();
+        assert_eq!(
+            err,
+            "Could not order pull requests #1, #2, #3, since their `depends-on` form a cycle"
+        );
+
+        let err = order(r##"{ number = 1, depends-on = [1] }"##)
+            .unwrap_err()
+            .to_string();
+        assert!(
+            err.contains("#1, since their `depends-on` form a cycle"),
+            "{err}"
+        );
+    }
+
+    #[derive(Deserialize)]
+    struct Patches {
+        patches: Vec<PatchEntry>,
+    }
+
+    /// Parses entries of `patches` the way the config does
+    fn parse_patches(entries: &str) -> Result<Vec<PatchEntry>, String> {
+        toml::from_str::<Patches>(&format!("patches = [{entries}]"))
+            .map(|config| config.patches)
+            .map_err(|err| err.message().to_owned())
+    }
+
+    #[test]
+    fn parses_patches() {
+        let patches = parse_patches(
+            r#""my-patch", "https://example.com/fix.patch", { name = "other", on-failure = "skip" }"#,
+        )
+        .unwrap();
+
+        assert_eq!(patches[0].source, PatchSource::File("my-patch".into()));
+        assert_eq!(
+            patches[1].source,
+            PatchSource::Url {
+                url: "https://example.com/fix.patch".into(),
+                sha256: None
+            }
+        );
+        assert_eq!(patches[2].source, PatchSource::File("other".into()));
+        assert_eq!(patches[2].on_failure, Some(PatchFailure::Skip));
+    }
+
+    #[test]
+    fn names_bad_keys_and_values_of_patch_tables() {
+        let err = parse_patches(r#"{ name = "my-patch", on-failure = "skipp" }"#).unwrap_err();
+        assert!(err.contains("skipp"), "{err}");
+        assert!(!err.contains("untagged"), "{err}");
+
+        let err = parse_patches(r#"{ name = "my-patch", three-ways = true }"#).unwrap_err();
+        assert!(err.contains("three-ways"), "{err}");
+
+        let err = parse_patches(r#"{ url = "my-patch" }"#).unwrap_err();
+        assert!(err.contains("is not a URL"), "{err}");
+    }
+}
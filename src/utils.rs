@@ -1,8 +1,19 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
 use anyhow::anyhow;
+use colored::Colorize;
 use rand::Rng;
-use reqwest::{header::USER_AGENT, Client};
+use reqwest::{
+    header::{HeaderMap, HeaderValue, ACCEPT, AUTHORIZATION, USER_AGENT},
+    Client, Response, StatusCode,
+};
+
+use serde::de::DeserializeOwned;
+
+use crate::{flags::IS_JSON, forge::Forge, trace, APP_NAME};
 
-use crate::types::GitHubResponse;
+/// Environment variables which can hold a GitHub token, in order of priority
+pub static GITHUB_TOKEN_ENV_VARS: [&str; 2] = ["GITHUB_TOKEN", "GH_TOKEN"];
 
 pub fn with_uuid(s: &str) -> String {
     format!(
@@ -32,26 +43,203 @@ pub fn normalize_commit_msg(commit_msg: &str) -> String {
 }
 
 pub fn display_link(text: &str, url: &str) -> String {
+    // Scripts reading the output have no use for escape codes
+    if *IS_JSON {
+        return text.to_owned();
+    }
+
     format!("\u{1b}]8;;{}\u{1b}\\{}\u{1b}]8;;\u{1b}\\", url, text)
 }
 
-pub async fn make_request(client: &Client, url: &str) -> anyhow::Result<GitHubResponse> {
-    let request = client
-        .get(url)
-        .header(USER_AGENT, "{APP_NAME}")
-        .send()
-        .await;
+/// Shortens a commit hash the way git displays it
+pub fn short_hash(commit: &str) -> &str {
+    commit.get(..7).unwrap_or(commit)
+}
+
+/// How a pull request is referred to in messages: `#123` for a pull request of `repo`, and as
+/// written in the config otherwise, e.g. `owner/repo#123`
+pub fn pull_request_label(pull_request: &str) -> String {
+    if pull_request.chars().all(|ch| ch.is_ascii_digit()) {
+        format!("#{pull_request}")
+    } else {
+        pull_request.into()
+    }
+}
+
+/// Formats a pull request as its number and title, linking to it
+///
+/// Branches without a pull request are only shown as their title, which already names them
+pub fn format_pr(pull_request: &str, title: &str, url: &str) -> String {
+    let label = if pull_request.contains(':') {
+        String::new()
+    } else {
+        format!("{} ", pull_request_label(pull_request))
+    };
+
+    display_link(
+        &format!("{}{}", label.bright_blue(), title.bright_blue().italic()),
+        url,
+    )
+}
+
+/// Finds a token to authenticate requests to the GitHub API with
+///
+/// Sources are checked in the following order, the first one found wins:
+/// 1. `GITHUB_TOKEN` or `GH_TOKEN` environment variables
+/// 2. The `github-token` key of the configuration file
+/// 3. Credentials stored by the `gh` CLI, via `gh auth token`
+pub fn get_github_token(config_token: Option<&str>) -> Option<String> {
+    for var in GITHUB_TOKEN_ENV_VARS {
+        if let Some(token) = std::env::var(var).ok().filter(|token| !token.is_empty()) {
+            trace!("Using GitHub token from the {var} environment variable");
+            return Some(token);
+        }
+    }
+
+    if let Some(token) = config_token.filter(|token| !token.is_empty()) {
+        trace!("Using GitHub token from the configuration file");
+        return Some(token.into());
+    }
+
+    let gh_output = std::process::Command::new("gh")
+        .args(["auth", "token"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())?;
+
+    let token = String::from_utf8_lossy(&gh_output.stdout).trim().to_owned();
+
+    if token.is_empty() {
+        None
+    } else {
+        trace!("Using GitHub token from the gh CLI");
+        Some(token)
+    }
+}
+
+/// Creates a client for the API of `forge`, which sends the given token with every request if there is one
+pub fn make_client(forge: Forge, token: Option<&str>) -> anyhow::Result<Client> {
+    let mut headers = HeaderMap::new();
+
+    headers.insert(USER_AGENT, HeaderValue::from_static(APP_NAME));
+    if forge == Forge::GitHub {
+        headers.insert(
+            ACCEPT,
+            HeaderValue::from_static("application/vnd.github+json"),
+        );
+    }
+
+    if let Some(token) = token {
+        let mut authorization = HeaderValue::from_str(&format!("Bearer {token}"))
+            .map_err(|err| anyhow!("The GitHub token contains invalid characters.\n{err}"))?;
+        authorization.set_sensitive(true);
+        headers.insert(AUTHORIZATION, authorization);
+    }
+
+    Client::builder()
+        .default_headers(headers)
+        .build()
+        .map_err(|err| anyhow!("Could not create HTTP client.\n{err}"))
+}
+
+/// Reads a numeric header of a response
+fn numeric_header(res: &Response, name: &str) -> Option<u64> {
+    res.headers()
+        .get(name)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse().ok())
+}
+
+/// Reads a rate limit header of an API response, such as `x-ratelimit-remaining` on GitHub or
+/// `ratelimit-remaining` on GitLab
+fn rate_limit_header(res: &Response, forge: Forge, name: &str) -> Option<u64> {
+    numeric_header(res, &format!("{}{name}", forge.rate_limit_header_prefix()))
+}
+
+/// Seconds left until the rate limit resets, which is given as seconds since the Unix epoch
+fn seconds_until(reset: u64) -> u64 {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or(Duration::ZERO)
+        .as_secs();
+
+    reset.saturating_sub(now)
+}
+
+/// Explains that the API of `forge` refuses requests until its rate limit resets
+///
+/// The limit and the seconds until it resets are left out if the response didn't report them
+fn rate_limit_message(forge: Forge, limit: Option<u64>, reset: Option<u64>, url: &str) -> String {
+    let hint = match forge {
+        // Unauthenticated requests are limited to 60 per hour
+        Forge::GitHub if limit.is_some_and(|limit| limit <= 60) => format!(
+            "\nTo raise the limit, set the {} environment variable, \
+            add `github-token` to your config or log in with `gh auth login`",
+            GITHUB_TOKEN_ENV_VARS.join(" or ")
+        ),
+        Forge::GitHub => String::new(),
+        Forge::GitLab | Forge::Gitea => format!(
+            "\nRequests with a token from the {} environment variable have a higher limit",
+            forge.token_env_vars().join(" or ")
+        ),
+    };
+
+    let limit = limit.map_or_else(String::new, |limit| {
+        format!(": 0 of {limit} requests remaining")
+    });
+    let reset = reset.map_or_else(
+        || "an unknown amount of time".into(),
+        |seconds| format!("{}m {}s", seconds / 60, seconds % 60),
+    );
+
+    format!(
+        "{} API rate limit exceeded{limit}. The limit resets in {reset}.\nRequested URL: {url}{hint}",
+        forge.name()
+    )
+}
+
+/// Requests JSON from the API of `forge`
+pub async fn make_request<T: DeserializeOwned>(
+    client: &Client,
+    forge: Forge,
+    url: &str,
+) -> anyhow::Result<T> {
+    let request = client.get(url).send().await;
 
     match request {
         Ok(res) if res.status().is_success() => {
+            if let (Some(remaining), Some(limit)) = (
+                rate_limit_header(&res, forge, "remaining"),
+                rate_limit_header(&res, forge, "limit"),
+            ) {
+                trace!(
+                    "{} API requests remaining: {remaining} of {limit}",
+                    forge.name()
+                );
+            }
+
             let out = res.text().await?;
 
-            let response: GitHubResponse = serde_json::from_str(&out).map_err(|err| {
+            let response: T = serde_json::from_str(&out).map_err(|err| {
                 anyhow!("Could not parse response.\n{out}. Could not parse because: \n{err}")
             })?;
 
             Ok(response)
         }
+        // GitHub refuses requests over the limit with 403 and the others with 429, which may not say
+        // how many requests are left
+        Ok(res)
+            if res.status() == StatusCode::TOO_MANY_REQUESTS
+                || res.status() == StatusCode::FORBIDDEN
+                    && rate_limit_header(&res, forge, "remaining") == Some(0) =>
+        {
+            let limit = rate_limit_header(&res, forge, "limit");
+            let reset = rate_limit_header(&res, forge, "reset")
+                .map(seconds_until)
+                .or_else(|| numeric_header(&res, "retry-after"));
+
+            Err(anyhow!(rate_limit_message(forge, limit, reset, url)))
+        }
         Ok(res) => {
             let status = res.status();
             let text = res.text().await?;
@@ -65,10 +253,22 @@ pub async fn make_request(client: &Client, url: &str) -> anyhow::Result<GitHubRe
     }
 }
 
+/// Prints output meant for humans, which goes to stderr with `--output=json` to leave stdout to the report
+#[macro_export]
+macro_rules! human {
+    ($($arg:tt)*) => {{
+        if *$crate::flags::IS_JSON {
+            eprintln!($($arg)*)
+        } else {
+            println!($($arg)*)
+        }
+    }};
+}
+
 #[macro_export]
 macro_rules! success {
     ($($arg:tt)*) => {{
-        println!("{}{}{}",
+        $crate::human!("{}{}{}",
             $crate::INDENT,
             colored::Colorize::bold(colored::Colorize::bright_green("✓ ")),
             format!($($arg)*))
@@ -121,3 +321,103 @@ macro_rules! confirm_prompt {
             .unwrap()
     }};
 }
+
+#[cfg(test)]
+mod tests {
+    use serde_json::Value;
+
+    use super::*;
+    use crate::forge::tests::{json_response, listen};
+
+    /// Answers every request with `response`, and makes a request for `forge` to it
+    async fn request(forge: Forge, response: &'static str) -> anyhow::Result<Value> {
+        let repo = listen(move |_| response.to_owned(), forge).await;
+        let client = make_client(forge, None).unwrap();
+
+        make_request(&client, forge, &format!("{}/api", repo.base_url)).await
+    }
+
+    #[test]
+    fn names_forge_whose_rate_limit_is_exceeded() {
+        let url = "https://api.github.com/repos/helix-editor/helix/pulls/1";
+        let github = rate_limit_message(Forge::GitHub, Some(60), Some(300), url);
+        assert!(
+            github.starts_with("GitHub API rate limit exceeded"),
+            "{github}"
+        );
+        assert!(github.contains("gh auth login"), "{github}");
+        assert!(github.contains("0 of 60 requests remaining"), "{github}");
+        assert!(github.contains("5m 0s"), "{github}");
+
+        let authenticated = rate_limit_message(Forge::GitHub, Some(5000), Some(300), url);
+        assert!(!authenticated.contains("gh auth login"), "{authenticated}");
+
+        let url = "https://codeberg.org/api/v1/repos/owner/repo/pulls/1";
+        let gitea = rate_limit_message(Forge::Gitea, Some(100), Some(300), url);
+        assert!(
+            gitea.starts_with("Gitea API rate limit exceeded"),
+            "{gitea}"
+        );
+        assert!(gitea.contains("GITEA_TOKEN"), "{gitea}");
+        assert!(!gitea.contains("GitHub"), "{gitea}");
+    }
+
+    #[tokio::test]
+    async fn reads_rate_limit_headers_of_gitlab() {
+        let err = request(
+            Forge::GitLab,
+            "HTTP/1.1 429 Too Many Requests\r\nRateLimit-Limit: 2000\r\n\
+            RateLimit-Remaining: 0\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+        )
+        .await
+        .unwrap_err()
+        .to_string();
+
+        assert!(
+            err.starts_with("GitLab API rate limit exceeded: 0 of 2000 requests remaining"),
+            "{err}"
+        );
+        assert!(err.contains("GITLAB_TOKEN"), "{err}");
+    }
+
+    #[tokio::test]
+    async fn too_many_requests_is_rate_limited_without_headers() {
+        let err = request(
+            Forge::Gitea,
+            "HTTP/1.1 429 Too Many Requests\r\nRetry-After: 90\r\n\
+            Content-Length: 0\r\nConnection: close\r\n\r\n",
+        )
+        .await
+        .unwrap_err()
+        .to_string();
+
+        assert!(
+            err.starts_with("Gitea API rate limit exceeded. The limit resets in 1m 30s"),
+            "{err}"
+        );
+    }
+
+    #[tokio::test]
+    async fn asks_only_github_for_its_media_type() {
+        for (forge, expected) in [
+            (Forge::GitHub, true),
+            (Forge::GitLab, false),
+            (Forge::Gitea, false),
+        ] {
+            let repo = listen(
+                |request| {
+                    let accepts_github = request
+                        .to_lowercase()
+                        .contains("accept: application/vnd.github+json");
+                    json_response("200 OK", "", &accepts_github.to_string())
+                },
+                forge,
+            )
+            .await;
+            let client = make_client(forge, None).unwrap();
+
+            let accepts_github: bool = make_request(&client, forge, &repo.base_url).await.unwrap();
+            assert_eq!(accepts_github, expected, "{forge:?}");
+        }
+    }
+}
@@ -0,0 +1,359 @@
+//! Runs the `patchy` binary in a throwaway repository, whose upstream and forge API are served locally
+//!
+//! Each test binary only uses some of these helpers
+#![allow(dead_code)]
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs,
+    io::{Read, Write},
+    net::TcpListener,
+    path::{Path, PathBuf},
+    process::{Command, Output},
+    sync::{Arc, Mutex},
+    thread,
+};
+
+use tempfile::TempDir;
+
+/// Response of the forge API by request path
+type Routes = Arc<Mutex<HashMap<String, (u16, String)>>>;
+
+pub struct Fixture {
+    dir: TempDir,
+    /// Address of the local server acting as the forge
+    pub address: String,
+    routes: Routes,
+}
+
+impl Fixture {
+    /// A fork of an upstream repository whose `main` branch has a `README.md`, and a contributor's
+    /// clone of it from which pull requests are opened
+    pub fn new() -> Self {
+        let dir = tempfile::tempdir().unwrap();
+        let routes = Routes::default();
+        let address = serve(Arc::clone(&routes));
+
+        let fixture = Self {
+            dir,
+            address,
+            routes,
+        };
+
+        fs::create_dir(fixture.home()).unwrap();
+        fs::write(
+            fixture.home().join(".gitconfig"),
+            format!(
+                "[user]\n\tname = Test\n\temail = test@example.com\n\
+                [init]\n\tdefaultBranch = main\n\
+                [advice]\n\tdetachedHead = false\n\
+                [url \"{}\"]\n\tinsteadOf = {}\n",
+                fixture.upstream().display(),
+                fixture.repository_url(),
+            ),
+        )
+        .unwrap();
+
+        fs::create_dir(fixture.upstream()).unwrap();
+        fixture.git(&fixture.upstream(), &["init", "--quiet"]);
+        fixture.commit(
+            &fixture.upstream(),
+            &[("README.md", "hello\n")],
+            "Initial commit",
+        );
+
+        for clone in [fixture.fork(), fixture.contributor()] {
+            fixture.git(
+                fixture.dir.path(),
+                &[
+                    "clone",
+                    "--quiet",
+                    fixture.upstream().to_str().unwrap(),
+                    clone.to_str().unwrap(),
+                ],
+            );
+        }
+
+        fixture
+    }
+
+    fn home(&self) -> PathBuf {
+        self.dir.path().join("home")
+    }
+
+    pub fn upstream(&self) -> PathBuf {
+        self.dir.path().join("upstream")
+    }
+
+    /// The user's checkout, in which `patchy` runs
+    pub fn fork(&self) -> PathBuf {
+        self.dir.path().join("fork")
+    }
+
+    pub fn contributor(&self) -> PathBuf {
+        self.dir.path().join("contributor")
+    }
+
+    /// URL of the repository on the forge, which git fetches from `upstream` instead
+    pub fn repository_url(&self) -> String {
+        format!("http://{}/owner/repo.git", self.address)
+    }
+
+    /// Runs git in `dir`, panicking if it fails
+    pub fn git(&self, dir: &Path, args: &[&str]) -> String {
+        let output = Command::new("git")
+            .args(args)
+            .current_dir(dir)
+            .envs(self.env())
+            .output()
+            .unwrap();
+
+        assert!(
+            output.status.success(),
+            "git {args:?} failed\n{}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+
+        String::from_utf8_lossy(&output.stdout).trim_end().into()
+    }
+
+    /// Commits `files` to the current branch of `dir`, returning the commit
+    pub fn commit(&self, dir: &Path, files: &[(&str, &str)], message: &str) -> String {
+        for (name, contents) in files {
+            let path = dir.join(name);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, contents).unwrap();
+        }
+
+        self.git(dir, &["add", "--all"]);
+        self.git(dir, &["commit", "--quiet", "--message", message]);
+        self.git(dir, &["rev-parse", "HEAD"])
+    }
+
+    /// Opens a pull request from a new branch of the contributor, which changes `files` on top of
+    /// upstream's `main`
+    ///
+    /// Returns the head commit of the pull request
+    pub fn pull_request(&self, number: u32, files: &[(&str, &str)]) -> String {
+        let branch = format!("feature-{number}");
+        let head = self.branch(&branch, files);
+
+        self.set_pull_request(number, &branch, &head, "open", false);
+
+        head
+    }
+
+    /// Creates a branch of the contributor which changes `files` on top of upstream's `main`,
+    /// returning its commit
+    pub fn branch(&self, branch: &str, files: &[(&str, &str)]) -> String {
+        let contributor = self.contributor();
+
+        self.git(&contributor, &["fetch", "--quiet", "origin"]);
+        self.git(
+            &contributor,
+            &["switch", "--quiet", "--create", branch, "origin/main"],
+        );
+        let head = self.commit(&contributor, files, &format!("Change on {branch}"));
+        self.git(&contributor, &["switch", "--quiet", "--detach"]);
+
+        head
+    }
+
+    /// Adds a commit which changes `files` to a branch of the contributor, returning it
+    pub fn push(&self, branch: &str, files: &[(&str, &str)]) -> String {
+        let contributor = self.contributor();
+
+        self.git(&contributor, &["switch", "--quiet", branch]);
+        let head = self.commit(&contributor, files, &format!("Change on {branch}"));
+        self.git(&contributor, &["switch", "--quiet", "--detach"]);
+
+        head
+    }
+
+    /// A patch in the format of `git format-patch`, which changes `files` on top of upstream's `main`
+    pub fn patch(&self, files: &[(&str, &str)], message: &str) -> String {
+        let contributor = self.contributor();
+
+        self.git(&contributor, &["fetch", "--quiet", "origin"]);
+        self.git(
+            &contributor,
+            &["switch", "--quiet", "--detach", "origin/main"],
+        );
+        self.commit(&contributor, files, message);
+
+        self.git(&contributor, &["format-patch", "-1", "--stdout"]) + "\n"
+    }
+
+    /// Makes the API respond to a lookup of pull request `number` with the given details
+    pub fn set_pull_request(
+        &self,
+        number: u32,
+        branch: &str,
+        head: &str,
+        state: &str,
+        merged: bool,
+    ) {
+        let body = format!(
+            r#"{{
+                "title": "Pull request {number}",
+                "html_url": "http://{address}/owner/repo/pulls/{number}",
+                "state": "{state}",
+                "merged": {merged},
+                "mergeable": true,
+                "updated_at": "2026-01-01T00:00:00Z",
+                "head": {{
+                    "ref": "{branch}",
+                    "sha": "{head}",
+                    "repo": {{ "clone_url": "{clone_url}", "ssh_url": "{clone_url}" }}
+                }}
+            }}"#,
+            address = self.address,
+            clone_url = self.contributor().display(),
+        );
+
+        self.route(
+            &format!("/api/v1/repos/owner/repo/pulls/{number}"),
+            200,
+            &body,
+        );
+    }
+
+    /// Makes the local server respond to `path`
+    pub fn route(&self, path: &str, status: u16, body: &str) {
+        self.routes
+            .lock()
+            .unwrap()
+            .insert(path.into(), (status, body.into()));
+    }
+
+    /// Writes `.patchy/config.toml` with `extra` keys after those naming the repository, and
+    /// commits it to the fork
+    pub fn config(&self, extra: &str) {
+        self.write_config(&format!(
+            "repo = \"http://{}/owner/repo\"\nforge = \"gitea\"\n\
+            remote-branch = \"main\"\nlocal-branch = \"patched\"\n{extra}",
+            self.address
+        ));
+    }
+
+    /// Replaces `.patchy/config.toml` and commits it to the fork
+    pub fn write_config(&self, config: &str) {
+        self.commit(
+            &self.fork(),
+            &[(".patchy/config.toml", config)],
+            "Configure patchy",
+        );
+    }
+
+    /// Environment of every process, which keeps the user's git config and tokens out
+    pub fn env(&self) -> Vec<(&'static str, PathBuf)> {
+        vec![
+            ("HOME", self.home()),
+            ("GIT_CONFIG_GLOBAL", self.home().join(".gitconfig")),
+            ("GIT_CONFIG_NOSYSTEM", "1".into()),
+            ("NO_COLOR", "1".into()),
+        ]
+    }
+
+    /// Makes git commands of this process run in the fork, with the same environment as `patchy`
+    pub fn enter(&self) {
+        std::env::set_current_dir(self.fork()).unwrap();
+
+        for (name, value) in self.env() {
+            std::env::set_var(name, value);
+        }
+    }
+
+    /// Runs `patchy` in the fork
+    pub fn patchy(&self, args: &[&str]) -> Output {
+        Command::new(env!("CARGO_BIN_EXE_patchy"))
+            .args(args)
+            .current_dir(self.fork())
+            .envs(self.env())
+            .env_remove("GITHUB_TOKEN")
+            .env_remove("GH_TOKEN")
+            .env_remove("GITEA_TOKEN")
+            .env_remove("FORGEJO_TOKEN")
+            .output()
+            .unwrap()
+    }
+
+    /// Branches of the fork
+    pub fn branches(&self) -> Vec<String> {
+        self.git(&self.fork(), &["branch", "--format=%(refname:short)"])
+            .lines()
+            .map(Into::into)
+            .collect()
+    }
+
+    /// Remotes of the fork
+    pub fn remotes(&self) -> Vec<String> {
+        self.git(&self.fork(), &["remote"])
+            .lines()
+            .map(Into::into)
+            .collect()
+    }
+
+    /// Contents of `path` at `branch` of the fork
+    pub fn show(&self, branch: &str, path: &str) -> String {
+        self.git(&self.fork(), &["show", &format!("{branch}:{path}")])
+    }
+}
+
+/// Everything the command printed, for assertions and for the message of a failed one
+pub fn output(output: &Output) -> String {
+    format!(
+        "{}{}",
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr)
+    )
+}
+
+/// Contents of every file in `dir` and its subdirectories, by path
+pub fn snapshot(dir: &Path) -> BTreeMap<PathBuf, Vec<u8>> {
+    let mut files = BTreeMap::new();
+
+    for entry in fs::read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+
+        if path.is_dir() {
+            files.extend(snapshot(&path));
+        } else {
+            files.insert(path.clone(), fs::read(path).unwrap());
+        }
+    }
+
+    files
+}
+
+/// Answers each request with the response `routes` has for its path, or 404
+fn serve(routes: Routes) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap().to_string();
+
+    thread::spawn(move || {
+        for mut stream in listener.incoming().flatten() {
+            let mut request = vec![0; 8192];
+            let read = stream.read(&mut request).unwrap_or_default();
+            let request = String::from_utf8_lossy(&request[..read]);
+            let path = request.split_whitespace().nth(1).unwrap_or_default();
+
+            let (status, body) = routes
+                .lock()
+                .unwrap()
+                .get(path)
+                .cloned()
+                .unwrap_or((404, r#"{"message":"Not Found"}"#.into()));
+
+            let _ = write!(
+                stream,
+                "HTTP/1.1 {status} {}\r\nContent-Type: application/json\r\n\
+                Content-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                if status == 200 { "OK" } else { "Error" },
+                body.len()
+            );
+        }
+    });
+
+    address
+}
@@ -0,0 +1,55 @@
+mod common;
+
+use std::fs;
+
+use common::{output, snapshot, Fixture};
+
+#[test]
+fn reports_merges_without_changing_the_repository() {
+    let fixture = Fixture::new();
+    fixture.pull_request(1, &[("a.txt", "a\n")]);
+    fixture.pull_request(2, &[("README.md", "two\n")]);
+    fixture.pull_request(3, &[("README.md", "three\n")]);
+    fixture.config(r#"pull-requests = ["1", "2", "3"]"#);
+
+    let head = fixture.git(&fixture.fork(), &["rev-parse", "HEAD"]);
+
+    let run = fixture.patchy(&["run", "--dry-run"]);
+    let output = output(&run);
+
+    assert_eq!(run.status.code(), Some(2), "{output}");
+    assert!(output.contains("Dry run report"), "{output}");
+    assert!(output.contains("#1 Pull request 1"), "{output}");
+    assert!(output.contains("has merge conflicts"), "{output}");
+
+    assert_eq!(fixture.branches(), ["main"]);
+    assert_eq!(fixture.remotes(), ["origin"]);
+    assert_eq!(fixture.git(&fixture.fork(), &["rev-parse", "HEAD"]), head);
+    assert_eq!(fixture.git(&fixture.fork(), &["status", "--porcelain"]), "");
+}
+
+#[test]
+fn leaves_the_config_directory_untouched() {
+    let fixture = Fixture::new();
+    let patch = fixture.patch(&[("b.txt", "b\n")], "Add b");
+    fixture.route("/patches/b.patch", 200, &patch);
+    fixture.config(&format!(
+        "pull-requests = []\npatches = [\"http://{}/patches/b.patch\"]",
+        fixture.address
+    ));
+
+    // A patch downloaded by an earlier run, which the config no longer has
+    let cache = fixture.fork().join(".patchy/cache");
+    fs::create_dir_all(&cache).unwrap();
+    fs::write(cache.join(".gitignore"), "*\n").unwrap();
+    fs::write(cache.join("0000.patch"), "stale\n").unwrap();
+
+    let before = snapshot(&fixture.fork().join(".patchy"));
+
+    let run = fixture.patchy(&["run", "--dry-run"]);
+    let output = output(&run);
+
+    assert!(run.status.success(), "{output}");
+    assert!(output.contains("applies"), "{output}");
+    assert_eq!(snapshot(&fixture.fork().join(".patchy")), before);
+}
@@ -0,0 +1,74 @@
+mod common;
+
+use std::{fs, path::PathBuf};
+
+use common::{output, Fixture};
+
+/// Starts an interactive run which pauses at the conflict between pull requests 1 and 2,
+/// returning the worktree in which it paused
+fn pause(fixture: &Fixture) -> PathBuf {
+    fixture.pull_request(1, &[("README.md", "one\n")]);
+    fixture.pull_request(2, &[("README.md", "two\n")]);
+    fixture.config(r#"pull-requests = ["1", "2"]"#);
+
+    let run = fixture.patchy(&["run", "--yes", "--interactive"]);
+    let output = output(&run);
+    assert!(output.contains("Paused at pull request"), "{output}");
+    assert!(fixture.fork().join(".git/patchy/state.json").exists());
+
+    let worktrees = fixture.git(&fixture.fork(), &["worktree", "list", "--porcelain"]);
+    let worktree = worktrees
+        .lines()
+        .filter_map(|line| line.strip_prefix("worktree "))
+        .nth(1)
+        .unwrap_or_else(|| panic!("{worktrees}"));
+
+    PathBuf::from(worktree)
+}
+
+#[test]
+fn continues_once_the_conflict_is_resolved() {
+    let fixture = Fixture::new();
+    let worktree = pause(&fixture);
+
+    let run = fixture.patchy(&["run", "--yes"]);
+    assert!(!run.status.success(), "{}", output(&run));
+
+    fs::write(worktree.join("README.md"), "one and two\n").unwrap();
+    fixture.git(&worktree, &["add", "README.md"]);
+
+    let run = fixture.patchy(&["run", "--continue", "--yes"]);
+    assert!(run.status.success(), "{}", output(&run));
+
+    assert_eq!(fixture.show("patched", "README.md"), "one and two");
+    assert!(!fixture.fork().join(".git/patchy/state.json").exists());
+    assert!(!worktree.exists());
+}
+
+#[test]
+fn skips_the_conflicting_pull_request() {
+    let fixture = Fixture::new();
+    pause(&fixture);
+
+    let run = fixture.patchy(&["run", "--skip", "--yes"]);
+    assert!(
+        !fixture.fork().join(".git/patchy/state.json").exists(),
+        "{}",
+        output(&run)
+    );
+
+    assert_eq!(fixture.show("patched", "README.md"), "one");
+}
+
+#[test]
+fn aborts_without_changing_branches() {
+    let fixture = Fixture::new();
+    let worktree = pause(&fixture);
+
+    let run = fixture.patchy(&["run", "--abort"]);
+    assert!(run.status.success(), "{}", output(&run));
+
+    assert_eq!(fixture.branches(), ["main"]);
+    assert!(!fixture.fork().join(".git/patchy/state.json").exists());
+    assert!(!worktree.exists());
+}
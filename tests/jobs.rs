@@ -0,0 +1,36 @@
+mod common;
+
+use common::{output, Fixture};
+
+const CONFIG: &str = r#"
+repo = "owner/repo"
+remote-branch = "main"
+local-branch = "patched"
+pull-requests = ["1"]
+"#;
+
+#[test]
+fn rejects_fetching_no_pull_requests_at_a_time() {
+    let fixture = Fixture::new();
+    fixture.write_config(CONFIG);
+
+    let run = fixture.patchy(&["run", "--yes", "--jobs=0"]);
+    assert!(!run.status.success());
+    assert!(output(&run).contains("At least 1 pull request must be fetched at a time"));
+
+    fixture.write_config(&format!("{CONFIG}jobs = 0\n"));
+
+    let run = fixture.patchy(&["run", "--yes"]);
+    assert!(!run.status.success());
+    assert!(output(&run).contains("At least 1 pull request must be fetched at a time"));
+}
+
+#[test]
+fn rejects_invalid_jobs() {
+    let fixture = Fixture::new();
+    fixture.write_config(CONFIG);
+
+    let run = fixture.patchy(&["run", "--yes", "--jobs=many"]);
+    assert!(!run.status.success());
+    assert!(output(&run).contains("Invalid value for --jobs=: many"));
+}
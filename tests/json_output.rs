@@ -0,0 +1,55 @@
+mod common;
+
+use common::{output, Fixture};
+use serde_json::Value;
+
+fn json(output: &std::process::Output) -> Value {
+    serde_json::from_slice(&output.stdout)
+        .unwrap_or_else(|err| panic!("{err}\n{}", common::output(output)))
+}
+
+#[test]
+fn reports_each_pull_request_of_a_run() {
+    let fixture = Fixture::new();
+    let merged = fixture.pull_request(1, &[("README.md", "one\n")]);
+    fixture.pull_request(2, &[("README.md", "two\n")]);
+    fixture.config(r#"pull-requests = ["1", "2"]"#);
+
+    let run = fixture.patchy(&["run", "--yes", "--output=json"]);
+    let report = json(&run);
+
+    assert_eq!(report["local-branch"], "patched");
+    assert_eq!(report["pull-requests"][0]["number"], "1");
+    assert_eq!(report["pull-requests"][0]["title"], "Pull request 1");
+    assert_eq!(report["pull-requests"][0]["commit"], merged.as_str());
+    assert_eq!(report["pull-requests"][0]["outcome"], "merged");
+    assert_eq!(report["pull-requests"][1]["outcome"], "skipped");
+    assert!(report["pull-requests"][1]["error"]
+        .as_str()
+        .is_some_and(|error| error.contains("merge conflicts")));
+
+    // Messages for people go to stderr, without colors
+    let stderr = String::from_utf8_lossy(&run.stderr);
+    assert!(stderr.contains("Merged pull request"), "{stderr}");
+    assert!(!stderr.contains('\u{1b}'), "{stderr}");
+}
+
+#[test]
+fn reports_fetched_pull_requests_and_generated_patches() {
+    let fixture = Fixture::new();
+    let head = fixture.pull_request(1, &[("a.txt", "a\n")]);
+
+    let repo = format!("--repo-name=http://{}/owner/repo", fixture.address);
+    let fetch = fixture.patchy(&["pr-fetch", "1", &repo, "--forge=gitea", "--output=json"]);
+    assert!(fetch.status.success(), "{}", output(&fetch));
+    let report = json(&fetch);
+    assert_eq!(report["pull-requests"][0]["branch"], "1/feature-1");
+    assert_eq!(report["pull-requests"][0]["commit"], head.as_str());
+    assert_eq!(report["pull-requests"][0]["outcome"], "fetched");
+
+    let commit = fixture.commit(&fixture.fork(), &[("b.txt", "b\n")], "Add b");
+    let patch = fixture.patchy(&["gen-patch", &commit, "--output=json"]);
+    let report = json(&patch);
+    assert_eq!(report["patches"][0]["commit"], commit.as_str());
+    assert_eq!(report["patches"][0]["outcome"], "created");
+}
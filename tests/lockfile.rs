@@ -0,0 +1,134 @@
+mod common;
+
+use std::{fs, path::Path};
+
+use common::{output, Fixture};
+
+#[test]
+fn records_the_commits_of_a_run() {
+    let fixture = Fixture::new();
+    let head = fixture.pull_request(1, &[("a.txt", "a\n")]);
+    fixture.config(r#"pull-requests = ["1"]"#);
+
+    let run = fixture.patchy(&["run", "--yes"]);
+    assert!(run.status.success(), "{}", output(&run));
+
+    let base = fixture.git(&fixture.upstream(), &["rev-parse", "main"]);
+    let lockfile = fs::read_to_string(fixture.fork().join(".patchy/patchy.lock")).unwrap();
+
+    assert!(
+        lockfile.contains(&format!("commit = \"{base}\"")),
+        "{lockfile}"
+    );
+    assert!(
+        lockfile.contains(&format!("commit = \"{head}\"")),
+        "{lockfile}"
+    );
+    assert!(
+        lockfile.contains("title = \"Pull request 1\""),
+        "{lockfile}"
+    );
+    assert!(lockfile.contains("/owner/repo/pulls/1\""), "{lockfile}");
+}
+
+#[test]
+fn reproduces_the_locked_commits_until_updated() {
+    let fixture = Fixture::new();
+    fixture.pull_request(1, &[("a.txt", "a\n")]);
+    fixture.config(r#"pull-requests = ["1"]"#);
+
+    let run = fixture.patchy(&["run", "--yes"]);
+    assert!(run.status.success(), "{}", output(&run));
+
+    let head = fixture.push("feature-1", &[("a.txt", "b\n")]);
+    fixture.set_pull_request(1, "feature-1", &head, "open", false);
+
+    let run = fixture.patchy(&["run", "--yes", "--locked"]);
+    assert!(run.status.success(), "{}", output(&run));
+    assert_eq!(fixture.show("patched", "a.txt"), "a");
+
+    let update = fixture.patchy(&["update"]);
+    assert!(update.status.success(), "{}", output(&update));
+
+    let run = fixture.patchy(&["run", "--yes", "--locked"]);
+    assert!(run.status.success(), "{}", output(&run));
+    assert_eq!(fixture.show("patched", "a.txt"), "b");
+}
+
+/// Runs with pull request 1, then force-pushes its branch so that the locked commit is no longer
+/// on it, returning the locked commit
+///
+/// The fork is left without the commit as well, like a fresh clone of it would be
+fn force_push(fixture: &Fixture) -> String {
+    let locked = fixture.pull_request(1, &[("a.txt", "a\n")]);
+    fixture.config(r#"pull-requests = ["1"]"#);
+
+    let run = fixture.patchy(&["run", "--yes"]);
+    assert!(run.status.success(), "{}", output(&run));
+
+    let contributor = fixture.contributor();
+    fixture.git(
+        &contributor,
+        &["branch", "--force", "feature-1", "origin/main"],
+    );
+    let head = fixture.push("feature-1", &[("a.txt", "b\n")]);
+    fixture.set_pull_request(1, "feature-1", &head, "open", false);
+
+    prune(fixture, &fixture.fork());
+
+    locked
+}
+
+/// Removes commits which no branch of `repo` has
+fn prune(fixture: &Fixture, repo: &Path) {
+    fixture.git(repo, &["reflog", "expire", "--expire=now", "--all"]);
+    fixture.git(repo, &["gc", "--quiet", "--prune=now"]);
+}
+
+#[test]
+fn fetches_a_locked_commit_which_was_force_pushed_away() {
+    let fixture = Fixture::new();
+    force_push(&fixture);
+
+    let run = fixture.patchy(&["run", "--yes", "--locked"]);
+    assert!(run.status.success(), "{}", output(&run));
+    assert_eq!(fixture.show("patched", "a.txt"), "a");
+}
+
+#[test]
+fn fetches_a_locked_commit_from_the_ref_of_its_pull_request() {
+    let fixture = Fixture::new();
+    let locked = force_push(&fixture);
+
+    // The forge keeps the head of the pull request, even once the fork no longer has it
+    fixture.git(
+        &fixture.upstream(),
+        &[
+            "fetch",
+            "--quiet",
+            fixture.contributor().to_str().unwrap(),
+            &format!("{locked}:refs/pull/1/head"),
+        ],
+    );
+    prune(&fixture, &fixture.contributor());
+
+    let run = fixture.patchy(&["run", "--yes", "--locked"]);
+    assert!(run.status.success(), "{}", output(&run));
+    assert_eq!(fixture.show("patched", "a.txt"), "a");
+}
+
+#[test]
+fn reports_a_locked_commit_which_is_gone() {
+    let fixture = Fixture::new();
+    let locked = force_push(&fixture);
+    prune(&fixture, &fixture.contributor());
+
+    let run = fixture.patchy(&["run", "--yes", "--locked", "--strict"]);
+    let output = output(&run);
+    assert!(!run.status.success(), "{output}");
+    assert!(
+        output.contains(&format!("Commit {locked} is no longer available")),
+        "{output}"
+    );
+    assert!(output.contains("patchy update 1"), "{output}");
+}
@@ -0,0 +1,49 @@
+mod common;
+
+use std::fs;
+
+use common::{output, Fixture};
+
+/// Pull request 1 was merged, 2 was closed and 3 is still open
+fn fixture() -> Fixture {
+    let fixture = Fixture::new();
+    let merged = fixture.pull_request(1, &[("a.txt", "a\n")]);
+    fixture.set_pull_request(1, "feature-1", &merged, "closed", true);
+    let closed = fixture.pull_request(2, &[("b.txt", "b\n")]);
+    fixture.set_pull_request(2, "feature-2", &closed, "closed", false);
+    fixture.pull_request(3, &[("c.txt", "c\n")]);
+    fixture.config("pull-requests = [\n  \"1\",\n  # Still needed\n  \"2\",\n  \"3\",\n]\n");
+
+    fixture
+}
+
+#[test]
+fn skips_merged_and_closed_pull_requests() {
+    let fixture = fixture();
+
+    let run = fixture.patchy(&["run", "--yes"]);
+    let output = output(&run);
+    assert!(output.contains("was merged"), "{output}");
+    assert!(output.contains("was closed"), "{output}");
+
+    assert_eq!(fixture.show("patched", "c.txt"), "c");
+    let files = fixture.git(&fixture.fork(), &["ls-tree", "--name-only", "patched"]);
+    assert!(
+        !files.contains("a.txt") && !files.contains("b.txt"),
+        "{files}"
+    );
+}
+
+#[test]
+fn prunes_them_from_the_config() {
+    let fixture = fixture();
+
+    let run = fixture.patchy(&["run", "--yes", "--prune"]);
+    let output = output(&run);
+
+    let config = fs::read_to_string(fixture.fork().join(".patchy/config.toml")).unwrap();
+    assert!(
+        config.contains("pull-requests = [\n  \"3\",\n]"),
+        "{output}\n{config}"
+    );
+}
@@ -0,0 +1,105 @@
+mod common;
+
+use common::{output, Fixture};
+
+const LINES: &str = "1\n2\n3\n4\n5\n6\n7\n8\n9\n";
+
+/// A patch which changes line 8 of `lines.txt`, made before upstream changed line 5 next to it
+fn stale_patch(fixture: &Fixture) -> String {
+    fixture.commit(&fixture.upstream(), &[("lines.txt", LINES)], "Add lines");
+    let patch = fixture.patch(&[("lines.txt", &LINES.replace('8', "eight"))], "Change 8");
+    fixture.commit(
+        &fixture.upstream(),
+        &[("lines.txt", &LINES.replace('5', "five"))],
+        "Change 5",
+    );
+
+    patch
+}
+
+/// Commits the patches to `.patchy/` of the fork, along with the config
+fn config(fixture: &Fixture, patches: &[(&str, &str)], config: &str) {
+    let files: Vec<_> = patches
+        .iter()
+        .map(|(name, patch)| (format!(".patchy/{name}.patch"), *patch))
+        .collect();
+    let files: Vec<_> = files
+        .iter()
+        .map(|(path, patch)| (path.as_str(), *patch))
+        .collect();
+
+    fixture.commit(&fixture.fork(), &files, "Add patches");
+    fixture.config(&format!("pull-requests = []\n{config}"));
+}
+
+#[test]
+fn stops_at_a_patch_which_does_not_apply() {
+    let fixture = Fixture::new();
+    let stale = stale_patch(&fixture);
+    config(&fixture, &[("stale", &stale)], r#"patches = ["stale"]"#);
+
+    let run = fixture.patchy(&["run", "--yes"]);
+    assert!(!run.status.success(), "{}", output(&run));
+    assert_eq!(fixture.branches(), ["main"]);
+}
+
+#[test]
+fn applies_a_stale_patch_with_a_three_way_merge() {
+    let fixture = Fixture::new();
+    let stale = stale_patch(&fixture);
+    config(
+        &fixture,
+        &[("stale", &stale)],
+        r#"patches = [{ name = "stale", three-way = true }]"#,
+    );
+
+    let run = fixture.patchy(&["run", "--yes"]);
+    assert!(run.status.success(), "{}", output(&run));
+    assert_eq!(
+        fixture.show("patched", "lines.txt"),
+        LINES.replace('5', "five").replace('8', "eight").trim_end()
+    );
+}
+
+#[test]
+fn skips_a_patch_and_carries_on() {
+    let fixture = Fixture::new();
+    let stale = stale_patch(&fixture);
+    let other = fixture.patch(&[("b.txt", "b\n")], "Add b");
+    config(
+        &fixture,
+        &[("stale", &stale), ("other", &other)],
+        "patch-on-failure = \"skip\"\npatches = [\"stale\", \"other\"]",
+    );
+
+    let run = fixture.patchy(&["run", "--yes"]);
+    let stdout = String::from_utf8_lossy(&run.stdout);
+    assert_eq!(run.status.code(), Some(2), "{}", output(&run));
+    assert!(
+        stdout.contains("skipped patch stale: does not apply"),
+        "{stdout}"
+    );
+
+    assert_eq!(fixture.show("patched", "b.txt"), "b");
+    assert_eq!(
+        fixture.show("patched", "lines.txt"),
+        LINES.replace('5', "five").trim_end()
+    );
+}
+
+#[test]
+fn fixes_whitespace_of_added_lines() {
+    let fixture = Fixture::new();
+    let patch = fixture.patch(&[("b.txt", "b  \n")], "Add b");
+    config(
+        &fixture,
+        &[("trailing", &patch)],
+        r#"patches = [{ name = "trailing", whitespace = "fix" }]"#,
+    );
+
+    let run = fixture.patchy(&["run", "--yes"]);
+    assert!(run.status.success(), "{}", output(&run));
+    // Only `b` and the newline are left
+    let size = fixture.git(&fixture.fork(), &["cat-file", "-s", "patched:b.txt"]);
+    assert_eq!(size, "2");
+}
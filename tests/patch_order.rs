@@ -0,0 +1,65 @@
+mod common;
+
+use common::{output, Fixture};
+
+#[test]
+fn applies_patches_in_the_order_of_the_config() {
+    let fixture = Fixture::new();
+    let contributor = fixture.contributor();
+    let first = fixture.patch(&[("b.txt", "b\n")], "Add b");
+    fixture.commit(&contributor, &[("b.txt", "bb\n")], "Change b");
+    let second = fixture.git(&contributor, &["format-patch", "-1", "--stdout"]) + "\n";
+
+    // Applying them by file name would apply the second patch first, which fails
+    fixture.commit(
+        &fixture.fork(),
+        &[
+            (".patchy/z-first.patch", &first),
+            (".patchy/a-second.patch", &second),
+        ],
+        "Add patches",
+    );
+    fixture.config("pull-requests = []\npatches = [\"z-first\", \"a-second\"]");
+
+    let run = fixture.patchy(&["run", "--yes"]);
+    assert!(run.status.success(), "{}", output(&run));
+
+    assert_eq!(fixture.show("patched", "b.txt"), "bb");
+}
+
+#[test]
+fn stops_at_patches_missing_on_disk() {
+    let fixture = Fixture::new();
+    fixture.config("pull-requests = []\npatches = [\"missing\"]");
+
+    let run = fixture.patchy(&["run", "--yes"]);
+    let output = output(&run);
+
+    assert!(!run.status.success(), "{output}");
+    assert!(
+        output.contains("Could not find .patchy/missing.patch"),
+        "{output}"
+    );
+    assert_eq!(fixture.branches(), ["main"]);
+}
+
+#[test]
+fn warns_about_unlisted_patches() {
+    let fixture = Fixture::new();
+    let patch = fixture.patch(&[("b.txt", "b\n")], "Add b");
+    fixture.commit(
+        &fixture.fork(),
+        &[(".patchy/unlisted.patch", &patch)],
+        "Add patch",
+    );
+    fixture.config("pull-requests = []\npatches = []");
+
+    let run = fixture.patchy(&["run", "--yes"]);
+    let output = output(&run);
+
+    assert!(run.status.success(), "{output}");
+    assert!(output.contains("unlisted.patch"), "{output}");
+    assert!(!fixture
+        .git(&fixture.fork(), &["ls-tree", "--name-only", "patched"])
+        .contains("b.txt"));
+}
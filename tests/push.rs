@@ -0,0 +1,57 @@
+mod common;
+
+use common::{output, Fixture};
+
+/// Adds a bare repository as the `published` remote of the fork
+fn published(fixture: &Fixture) -> std::path::PathBuf {
+    let published = fixture.fork().with_file_name("published.git");
+    fixture.git(
+        &fixture.fork(),
+        &["init", "--quiet", "--bare", published.to_str().unwrap()],
+    );
+    fixture.git(
+        &fixture.fork(),
+        &["remote", "add", "published", published.to_str().unwrap()],
+    );
+
+    published
+}
+
+#[test]
+fn pushes_the_result_to_push_remote() {
+    let fixture = Fixture::new();
+    let published = published(&fixture);
+    fixture.pull_request(1, &[("a.txt", "a\n")]);
+    fixture
+        .config("pull-requests = [\"1\"]\npush-remote = \"published\"\npush-branch = \"nightly\"");
+
+    let run = fixture.patchy(&["run", "--yes", "--push"]);
+    let output = output(&run);
+    assert!(run.status.success(), "{output}");
+    assert!(output.contains(published.to_str().unwrap()), "{output}");
+
+    assert_eq!(
+        fixture.git(&published, &["rev-parse", "nightly"]),
+        fixture.git(&fixture.fork(), &["rev-parse", "patched"])
+    );
+}
+
+#[test]
+fn pushes_a_run_with_failures_only_when_told_to() {
+    let fixture = Fixture::new();
+    let published = published(&fixture);
+    fixture.pull_request(1, &[("README.md", "one\n")]);
+    fixture.pull_request(2, &[("README.md", "two\n")]);
+    fixture.config("pull-requests = [\"1\", \"2\"]\npush-remote = \"published\"");
+
+    let run = fixture.patchy(&["run", "--yes", "--push"]);
+    assert!(!run.status.success(), "{}", output(&run));
+    assert_eq!(fixture.git(&published, &["branch", "--list"]), "");
+
+    let run = fixture.patchy(&["run", "--yes", "--push-anyway"]);
+    assert_eq!(run.status.code(), Some(2), "{}", output(&run));
+    assert_eq!(
+        fixture.git(&published, &["rev-parse", "patched"]),
+        fixture.git(&fixture.fork(), &["rev-parse", "patched"])
+    );
+}
@@ -0,0 +1,50 @@
+//! Git runs in the repository of the current directory, so this binary has a single test which
+//! changes it
+
+mod common;
+
+use std::thread;
+
+use common::Fixture;
+use patchy::{
+    git_commands::add_remote_branch,
+    types::{Branch, BranchAndRemote, Remote},
+};
+
+#[test]
+fn adds_remotes_of_concurrent_fetches() {
+    let fixture = Fixture::new();
+    let heads: Vec<_> = (1..=8)
+        .map(|number| fixture.branch(&format!("feature-{number}"), &[("a.txt", "a\n")]))
+        .collect();
+
+    fixture.enter();
+
+    let fetches: Vec<_> = (1..=8)
+        .map(|number| {
+            let info = BranchAndRemote {
+                branch: Branch {
+                    upstream_branch_name: format!("feature-{number}"),
+                    local_branch_name: format!("{number}/feature-{number}"),
+                },
+                remote: Remote {
+                    repository_url: fixture.contributor().display().to_string(),
+                    local_remote_alias: format!("contributor-{number}"),
+                },
+            };
+
+            thread::spawn(move || add_remote_branch(&info, &None, &None))
+        })
+        .collect();
+
+    for fetch in fetches {
+        fetch.join().unwrap().unwrap();
+    }
+
+    assert_eq!(fixture.remotes().len(), 9);
+
+    for (number, head) in (1..=8).zip(heads) {
+        let branch = format!("{number}/feature-{number}");
+        assert_eq!(fixture.git(&fixture.fork(), &["rev-parse", &branch]), head);
+    }
+}
@@ -0,0 +1,79 @@
+mod common;
+
+use std::{fs, process::Command};
+
+use common::{output, Fixture};
+
+#[test]
+fn reuses_a_resolution_recorded_by_hand() {
+    let fixture = Fixture::new();
+    fixture.pull_request(1, &[("README.md", "one\n")]);
+    fixture.pull_request(2, &[("README.md", "two\n")]);
+    fixture.config(r#"pull-requests = ["1", "2"]"#);
+
+    let run = fixture.patchy(&["run", "--yes"]);
+    assert!(
+        output(&run).contains("Could not merge branch 2/feature-2"),
+        "{}",
+        output(&run)
+    );
+
+    // Resolve the conflict the way the message of the failed merge suggests, in a worktree of
+    // the branch patchy created
+    let fork = fixture.fork();
+    let resolve = fork.with_file_name("resolve");
+    fixture.git(
+        &fork,
+        &[
+            "worktree",
+            "add",
+            "--quiet",
+            resolve.to_str().unwrap(),
+            "patched",
+        ],
+    );
+    fixture.git(
+        &resolve,
+        &[
+            "fetch",
+            "--quiet",
+            fixture.contributor().to_str().unwrap(),
+            "feature-2",
+        ],
+    );
+    let merge = Command::new("git")
+        .args([
+            "-c",
+            "rerere.enabled=true",
+            "merge",
+            "--squash",
+            "FETCH_HEAD",
+        ])
+        .current_dir(&resolve)
+        .envs(fixture.env())
+        .output()
+        .unwrap();
+    assert!(!merge.status.success());
+    fs::write(resolve.join("README.md"), "one and two\n").unwrap();
+    fixture.git(&resolve, &["add", "README.md"]);
+    fixture.git(
+        &resolve,
+        &[
+            "-c",
+            "rerere.enabled=true",
+            "commit",
+            "--quiet",
+            "--message",
+            "Resolve",
+        ],
+    );
+    fixture.git(&fork, &["worktree", "remove", resolve.to_str().unwrap()]);
+
+    let run = fixture.patchy(&["run", "--yes"]);
+    let output = output(&run);
+    assert!(run.status.success(), "{output}");
+    assert!(output.contains("using a recorded resolution"), "{output}");
+
+    assert_eq!(fixture.show("patched", "README.md"), "one and two");
+    assert!(fork.join(".patchy/rr-cache").is_dir());
+}
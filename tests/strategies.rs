@@ -0,0 +1,89 @@
+mod common;
+
+use common::{output, Fixture};
+
+/// Runs with pull request 1 of two commits and pull request 2 of one commit, returning the
+/// subjects and parents of each commit the run added on top of upstream
+fn run(config: &str, strategy: &str) -> Vec<(String, usize)> {
+    let fixture = Fixture::new();
+    fixture.pull_request(1, &[("a.txt", "a\n")]);
+    fixture.push("feature-1", &[("a.txt", "aa\n")]);
+    fixture.pull_request(2, &[("b.txt", "b\n")]);
+    fixture.config(config);
+
+    let run = fixture.patchy(&["run", "--yes"]);
+    let output = output(&run);
+    assert!(run.status.success(), "{output}");
+    assert!(output.contains(&format!("using {strategy}")), "{output}");
+
+    assert_eq!(fixture.show("patched", "a.txt"), "aa");
+    assert_eq!(fixture.show("patched", "b.txt"), "b");
+
+    let base = fixture.git(&fixture.upstream(), &["rev-parse", "main"]);
+    fixture
+        .git(
+            &fixture.fork(),
+            &["log", "--format=%s%x00%p", &format!("{base}..patched")],
+        )
+        .lines()
+        .map(|line| {
+            let (subject, parents) = line.split_once('\0').unwrap();
+            (subject.into(), parents.split_whitespace().count())
+        })
+        .collect()
+}
+
+fn pull_request_commits(commits: &[(String, usize)]) -> usize {
+    commits
+        .iter()
+        .filter(|(subject, _)| subject == "Change on feature-1")
+        .count()
+}
+
+fn merges(commits: &[(String, usize)]) -> Vec<usize> {
+    commits
+        .iter()
+        .map(|(_, parents)| *parents)
+        .filter(|parents| *parents > 1)
+        .collect()
+}
+
+#[test]
+fn squashes_by_default() {
+    let commits = run(r#"pull-requests = ["1", "2"]"#, "squash");
+
+    assert_eq!(pull_request_commits(&commits), 0, "{commits:?}");
+    assert!(merges(&commits).is_empty(), "{commits:?}");
+}
+
+#[test]
+fn keeps_commits_with_a_merge_commit() {
+    let commits = run(
+        r#"pull-requests = [{ number = 1, strategy = "merge" }, "2"]"#,
+        "merge",
+    );
+
+    assert_eq!(pull_request_commits(&commits), 2, "{commits:?}");
+    assert_eq!(merges(&commits), [2], "{commits:?}");
+}
+
+#[test]
+fn rebases_each_commit() {
+    let commits = run(
+        "strategy = \"rebase\"\npull-requests = [\"1\", \"2\"]",
+        "rebase",
+    );
+
+    assert_eq!(pull_request_commits(&commits), 2, "{commits:?}");
+    assert!(merges(&commits).is_empty(), "{commits:?}");
+}
+
+#[test]
+fn merges_adjacent_pull_requests_together() {
+    let commits = run(
+        "strategy = \"octopus\"\npull-requests = [\"1\", \"2\"]",
+        "octopus",
+    );
+
+    assert_eq!(merges(&commits), [3], "{commits:?}");
+}
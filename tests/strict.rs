@@ -0,0 +1,43 @@
+mod common;
+
+use common::{output, Fixture};
+
+#[test]
+fn exits_with_code_2_listing_what_was_left_out() {
+    let fixture = Fixture::new();
+    fixture.pull_request(1, &[("README.md", "one\n")]);
+    fixture.pull_request(2, &[("README.md", "two\n")]);
+    fixture.config(r#"pull-requests = ["1", "2"]"#);
+
+    let run = fixture.patchy(&["run", "--yes"]);
+    let stdout = String::from_utf8_lossy(&run.stdout);
+
+    assert_eq!(run.status.code(), Some(2), "{}", output(&run));
+    assert!(
+        stdout.contains("skipped pull-request 2: has merge conflicts"),
+        "{stdout}"
+    );
+    assert_eq!(fixture.show("patched", "README.md"), "one");
+}
+
+#[test]
+fn leaves_local_branch_as_it_was_with_strict() {
+    let fixture = Fixture::new();
+    fixture.pull_request(1, &[("README.md", "one\n")]);
+    fixture.pull_request(2, &[("README.md", "two\n")]);
+    fixture.config(r#"pull-requests = ["1"]"#);
+
+    let run = fixture.patchy(&["run", "--yes"]);
+    assert!(run.status.success(), "{}", output(&run));
+    let patched = fixture.git(&fixture.fork(), &["rev-parse", "patched"]);
+
+    fixture.config(r#"pull-requests = ["1", "2"]"#);
+
+    let run = fixture.patchy(&["run", "--yes", "--strict"]);
+    assert!(!run.status.success(), "{}", output(&run));
+
+    assert_eq!(
+        fixture.git(&fixture.fork(), &["rev-parse", "patched"]),
+        patched
+    );
+}
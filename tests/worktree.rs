@@ -0,0 +1,51 @@
+mod common;
+
+use std::fs;
+
+use common::{output, Fixture};
+
+#[test]
+fn merges_without_touching_the_checkout() {
+    let fixture = Fixture::new();
+    fixture.pull_request(1, &[("a.txt", "a\n")]);
+    fixture.config(r#"pull-requests = ["1"]"#);
+
+    let head = fixture.git(&fixture.fork(), &["rev-parse", "HEAD"]);
+
+    let run = fixture.patchy(&["run", "--yes"]);
+    assert!(run.status.success(), "{}", output(&run));
+
+    assert_eq!(fixture.show("patched", "a.txt"), "a");
+    assert_eq!(
+        fixture.git(&fixture.fork(), &["branch", "--show-current"]),
+        "main"
+    );
+    assert_eq!(fixture.git(&fixture.fork(), &["rev-parse", "HEAD"]), head);
+    assert!(!fixture.fork().join("a.txt").exists());
+
+    let worktrees = fixture.git(&fixture.fork(), &["worktree", "list", "--porcelain"]);
+    assert_eq!(worktrees.matches("worktree ").count(), 1, "{worktrees}");
+}
+
+#[test]
+fn keeps_uncommitted_changes_when_a_merge_fails() {
+    let fixture = Fixture::new();
+    fixture.pull_request(1, &[("README.md", "one\n")]);
+    fixture.pull_request(2, &[("README.md", "two\n")]);
+    fixture.config(r#"pull-requests = ["1", "2"]"#);
+
+    fs::write(fixture.fork().join("README.md"), "mine\n").unwrap();
+
+    let run = fixture.patchy(&["run", "--yes", "--autostash"]);
+    let output = output(&run);
+    assert!(
+        output.contains("Could not merge branch 2/feature-2"),
+        "{output}"
+    );
+
+    assert_eq!(fixture.show("patched", "README.md"), "one");
+    assert_eq!(
+        fs::read_to_string(fixture.fork().join("README.md")).unwrap(),
+        "mine\n"
+    );
+}